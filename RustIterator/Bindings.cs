@@ -0,0 +1,675 @@
+// <auto-generated>
+// Generated by cs_iter_bindgen from the rust source. Don't edit this
+// by hand, run cs_iter_bindgen again instead.
+// </auto-generated>
+using System;
+using System.Runtime.InteropServices;
+using System.Text;
+
+namespace RustIterator
+{
+    /// <summary>
+    /// What `internal_iter` tells `C#` after each call.
+    ///
+    /// `AlreadyFinished`, `Panicked` and `Cancelled` are sticky: once an
+    /// iterator returns one of them, every later call returns the same
+    /// thing. `Exhausted` is only ever returned once, and is followed by
+    /// `AlreadyFinished`. `Item`, `Error` and `InvalidHandle` say nothing
+    /// about the next call.
+    /// </summary>
+    public enum RustIterStatus : int
+    {
+        /// <summary>
+        /// There was new data, and it was written to the data pointer
+        /// </summary>
+        Item = 0,
+        /// <summary>
+        /// The iterator just ran out of data, and was dropped
+        /// </summary>
+        Exhausted = 1,
+        /// <summary>
+        /// The iterator ran out of data on an earlier call
+        /// </summary>
+        AlreadyFinished = 2,
+        /// <summary>
+        /// The iterator panicked, either on this call or on an earlier one.
+        /// The iterator isn't touched anymore, and the panic message can be
+        /// fetched through `message`
+        /// </summary>
+        Panicked = 3,
+        /// <summary>
+        /// Either the call itself was wrong (like a null data pointer), or
+        /// the item was an error (see `form_fallible`), whose message can be
+        /// fetched through `message`. Either way, the iterator can still be
+        /// polled
+        /// </summary>
+        Error = 4,
+        /// <summary>
+        /// The iterator pointer doesn't point to an iterator
+        /// </summary>
+        InvalidHandle = 5,
+        /// <summary>
+        /// The iterator's `CancellationToken` was triggered, either during
+        /// this call or before it. The iterator was dropped, and any item it
+        /// produced in the meantime along with it
+        /// </summary>
+        Cancelled = 6,
+    }
+    /// <summary>
+    /// How many items an iterator has left, as told to `C#` by `size_hint`
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustSizeHint
+    {
+        /// <summary>
+        /// The least number of items left
+        /// </summary>
+        public UIntPtr Lower;
+        /// <summary>
+        /// The most number of items left, only meaningful if `has_upper` is set
+        /// </summary>
+        public UIntPtr Upper;
+        /// <summary>
+        /// Whether there is an upper bound at all
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)]
+        public bool HasUpper;
+        /// <summary>
+        /// Whether `lower` is exactly the number of items left, which is
+        /// only promised for iterators made with `form_exact`, and once an
+        /// iterator is done
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)]
+        public bool Exact;
+    }
+    /// <summary>
+    /// The "iterator" we pass to `C#`
+    ///
+    /// `T` can be anything, including types without a sensible `Default`
+    /// like `NonZeroU32`: the slot `C#` passes to `internal_iter` is only
+    /// ever written to, never read or dropped, so it can hold anything
+    /// (including garbage) beforehand.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustFFIIterator
+    {
+        /// <summary>
+        /// The function we pass `C#`. It's called by `C#` and recieves the
+        /// pointer to the `Box`ed iterator. It's instantiated for the exact
+        /// iterator type behind `pointer`, so there's no dynamic dispatch
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorNext
+        /// </remarks>
+        public IntPtr Next;
+        /// <summary>
+        /// A thin pointer to the iterator's state that gets leaked. Only the
+        /// functions next to it know what type it really points to
+        /// </summary>
+        public IntPtr Iterator;
+        /// <summary>
+        /// The function `C#` calls once it's done with the iterator, whether
+        /// or not it was run to the end. Frees `pointer`, after which none
+        /// of these functions may be called with it again
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorDestroy
+        /// </remarks>
+        public IntPtr Destroy;
+        /// <summary>
+        /// The function `C#` calls to get the panic message after
+        /// `internal_iter` returned `IterStatus::Panicked`, or the error
+        /// message after it returned `IterStatus::Error` for an error item
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorMessage
+        /// </remarks>
+        public IntPtr Message;
+        /// <summary>
+        /// The function `C#` calls to hand an item back once it's done with
+        /// it. Every item written by `internal_iter` belongs to `C#` until
+        /// it's passed here, and this works even after `destroy`
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorRelease
+        /// </remarks>
+        public IntPtr ReleaseItem;
+        /// <summary>
+        /// The function `C#` calls to find out how many items are left
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorSizeHint
+        /// </remarks>
+        public IntPtr SizeHint;
+        /// <summary>
+        /// The function `C#` calls to get many items at once, instead of
+        /// calling `internal_iter` for each of them
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorNextChunk
+        /// </remarks>
+        public IntPtr NextChunk;
+    }
+    /// <summary>
+    /// The function we pass `C#`. It's called by `C#` and recieves the
+    /// pointer to the `Box`ed iterator. It's instantiated for the exact
+    /// iterator type behind `pointer`, so there's no dynamic dispatch
+    /// </summary>
+    public unsafe delegate RustIterStatus RustIteratorNext(IntPtr iter, void* data);
+    /// <summary>
+    /// The function `C#` calls once it's done with the iterator, whether
+    /// or not it was run to the end. Frees `pointer`, after which none
+    /// of these functions may be called with it again
+    /// </summary>
+    public delegate void RustIteratorDestroy(IntPtr iter);
+    /// <summary>
+    /// The function `C#` calls to get the panic message after
+    /// `internal_iter` returned `IterStatus::Panicked`, or the error
+    /// message after it returned `IterStatus::Error` for an error item
+    /// </summary>
+    public unsafe delegate UIntPtr RustIteratorMessage(IntPtr iter, byte* buf, UIntPtr len);
+    /// <summary>
+    /// The function `C#` calls to hand an item back once it's done with
+    /// it. Every item written by `internal_iter` belongs to `C#` until
+    /// it's passed here, and this works even after `destroy`
+    /// </summary>
+    public unsafe delegate void RustIteratorRelease(void* item);
+    /// <summary>
+    /// The function `C#` calls to find out how many items are left
+    /// </summary>
+    public delegate RustSizeHint RustIteratorSizeHint(IntPtr iter);
+    /// <summary>
+    /// The function `C#` calls to get many items at once, instead of
+    /// calling `internal_iter` for each of them
+    /// </summary>
+    public unsafe delegate UIntPtr RustIteratorNextChunk(IntPtr iter, void* buf, UIntPtr len, out RustIterStatus status);
+    /// <summary>
+    /// A sequence owned by `C#` (like an `IEnumerable&lt;T&gt;`), handed to rust as
+    /// a context pointer and a pair of callbacks. It's a plain `Iterator`, so
+    /// rust functions exported by this crate can take one and use all the
+    /// usual combinators on it.
+    ///
+    /// Rust calls `next` until it returns false, and never after that, then
+    /// calls `release` exactly once when it drops the iterator, whether or
+    /// not it read it to the end.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustForeignIterator
+    {
+        /// <summary>
+        /// Passed back to `next` and `release`, see [Callbacks](crate#callbacks)
+        /// </summary>
+        public IntPtr Context;
+        /// <summary>
+        /// Writes the next item to the slot and returns true, or returns false
+        /// once there are no more items. The slot is uninitialized until it's
+        /// written to, and rust owns the item afterwards
+        /// </summary>
+        public IntPtr Next;
+        /// <summary>
+        /// Lets `C#` clean up its side, can be null if there's nothing to do
+        /// </summary>
+        public IntPtr Release;
+    }
+    /// <summary>
+    /// A callback `C#` gives rust to push items into, for when rust should
+    /// drive the loop instead of `C#` calling `internal_iter` over and over.
+    ///
+    /// Each item is only lent to `push` for the duration of the call, and
+    /// dropped by rust afterwards, so `C#` copies out what it needs and never
+    /// has to release anything.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustSink
+    {
+        /// <summary>
+        /// Passed back to `push`, see [Callbacks](crate#callbacks)
+        /// </summary>
+        public IntPtr Context;
+        /// <summary>
+        /// Takes the next item, returning false to stop early
+        /// </summary>
+        public IntPtr Push;
+    }
+    /// <summary>
+    /// The async "iterator" we pass to `C#`, for a `Stream` instead of an
+    /// `Iterator`.
+    ///
+    /// Instead of blocking until the next item is there, `request_next`
+    /// polls the stream once and returns, and the request completes through
+    /// its `CSharpCompletion` once the stream has something to say. Whoever
+    /// wakes the stream up polls it again right there, so there's no
+    /// executor or thread of our own behind it.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustFFIStream
+    {
+        /// <summary>
+        /// The function `C#` calls to ask for the next item, with the slot to
+        /// write it into and the callback to call once it did. Only one
+        /// request can be in flight at a time, another one completes with
+        /// `IterStatus::Error` right away
+        /// </summary>
+        public IntPtr RequestNext;
+        /// <summary>
+        /// A thin pointer to the stream's state that gets leaked
+        /// </summary>
+        public IntPtr Stream;
+        /// <summary>
+        /// The function `C#` calls once it's done with the stream. A request
+        /// still in flight completes with `IterStatus::AlreadyFinished`
+        /// </summary>
+        public IntPtr Destroy;
+        /// <summary>
+        /// The function `C#` calls to get the panic message after a request
+        /// completed with `IterStatus::Panicked`
+        /// </summary>
+        public IntPtr Message;
+        /// <summary>
+        /// The function `C#` calls to hand an item back once it's done with it
+        /// </summary>
+        public IntPtr ReleaseItem;
+    }
+    /// <summary>
+    /// The callback `C#` passes along with each request for an item, called
+    /// exactly once with how the request went. This is what `C#` completes
+    /// its `TaskCompletionSource` from.
+    ///
+    /// It may be called before `request_next` even returns, if the item was
+    /// already there, or later from whichever thread woke the stream up.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustCompletion
+    {
+        /// <summary>
+        /// Passed back to `complete`, see [Callbacks](crate#callbacks)
+        /// </summary>
+        public IntPtr Context;
+        /// <summary>
+        /// Takes the status of the request: `IterStatus::Item` if the slot
+        /// now holds an item, or whatever else stopped the stream
+        /// </summary>
+        public IntPtr Complete;
+    }
+    /// <summary>
+    /// Where rust put one of the `#[repr(C)]` types `C#` sees, or one of
+    /// their fields, as handed out by `abi_layout_info`.
+    ///
+    /// None of the generic types change shape with `T`, since `T` is only
+    /// ever behind a pointer, so each is described once. `BorrowedSlice` is
+    /// the exception, whose size depends on its owner: it's described
+    /// without one, which is the part `C#` reads.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustLayout
+    {
+        /// <summary>
+        /// The type, like `CSharpIteratorOut&lt;T&gt;`, as nul-terminated UTF-8
+        /// </summary>
+        public IntPtr Type;
+        /// <summary>
+        /// The field, as nul-terminated UTF-8, or null for the type itself
+        /// </summary>
+        public IntPtr Field;
+        /// <summary>
+        /// The size of the type or field, in bytes
+        /// </summary>
+        public UIntPtr Size;
+        /// <summary>
+        /// The alignment of the type or field, in bytes
+        /// </summary>
+        public UIntPtr Align;
+        /// <summary>
+        /// Where the field starts, in bytes, or 0 for the type itself
+        /// </summary>
+        public UIntPtr Offset;
+    }
+    /// <summary>
+    /// A `Vec&lt;T&gt;` with a layout we promise to keep, unlike `Vec&lt;T&gt;` itself
+    /// whose field order is up to the compiler. This is what collections
+    /// should be sent to `C#` as.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct FfiVec
+    {
+        /// <summary>
+        /// The pointer to the first element, dangling when `capacity` is 0
+        /// </summary>
+        public IntPtr Ptr;
+        /// <summary>
+        /// How many elements are initialized
+        /// </summary>
+        public UIntPtr Len;
+        /// <summary>
+        /// How many elements the allocation has room for
+        /// </summary>
+        public UIntPtr Capacity;
+    }
+    /// <summary>
+    /// A string handed to `C#` as a pointer and a length in code units,
+    /// either UTF-8 bytes (`FfiUtf8`) or UTF-16 chars (`FfiUtf16`, which is
+    /// what `C#` strings are made of). There's no nul terminator.
+    ///
+    /// Each one is its own allocation, owned by whoever holds it: it stays
+    /// valid until it's passed to `release_item` or to `ffi_utf8_free` /
+    /// `ffi_utf16_free`, even after the iterator it came from is destroyed.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct FfiUtf16
+    {
+        /// <summary>
+        /// The pointer to the first code unit, dangling when `len` is 0
+        /// </summary>
+        public IntPtr Ptr;
+        /// <summary>
+        /// How many code units there are
+        /// </summary>
+        public UIntPtr Len;
+    }
+    /// <summary>
+    /// A string handed to `C#` as a pointer and a length in code units,
+    /// either UTF-8 bytes (`FfiUtf8`) or UTF-16 chars (`FfiUtf16`, which is
+    /// what `C#` strings are made of). There's no nul terminator.
+    ///
+    /// Each one is its own allocation, owned by whoever holds it: it stays
+    /// valid until it's passed to `release_item` or to `ffi_utf8_free` /
+    /// `ffi_utf16_free`, even after the iterator it came from is destroyed.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct FfiUtf8
+    {
+        /// <summary>
+        /// The pointer to the first code unit, dangling when `len` is 0
+        /// </summary>
+        public IntPtr Ptr;
+        /// <summary>
+        /// How many code units there are
+        /// </summary>
+        public UIntPtr Len;
+    }
+    /// <summary>
+    /// Every function rust exports, with a factory method returning a
+    /// `RustIter<T>` or a `RustStream<T>` for each one that makes an
+    /// iterator or a stream
+    /// </summary>
+    public static class RustExports
+    {
+        [DllImport("cs_iter.dll")]
+        private static extern void get_iterator(out RustFFIIterator iter);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Creates an `Iterator&lt;Item=FfiVec&lt;usize&gt;&gt;` with each one counting up
+        /// to the current iteration
+        /// </summary>
+        public static RustIter<FfiVec> GetIterator()
+        {
+            get_iterator(out var iter);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<FfiVec>(iter);
+        }
+
+        [DllImport("cs_iter.dll")]
+        private static extern void get_string_iterator(out RustFFIIterator iter);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Creates an `Iterator&lt;Item=FfiUtf16&gt;` of lines, ready to be turned
+        /// into `C#` strings
+        /// </summary>
+        public static RustIter<FfiUtf16> GetStringIterator()
+        {
+            get_string_iterator(out var iter);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<FfiUtf16>(iter);
+        }
+
+        [DllImport("cs_iter.dll")]
+        private static extern void get_slow_iterator(out RustFFIIterator iter, IntPtr token);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Creates an endless `Iterator&lt;Item=u64&gt;` that takes a while per item,
+        /// and stops once `token` is triggered
+        /// </summary>
+        public static RustIter<ulong> GetSlowIterator(IntPtr token)
+        {
+            get_slow_iterator(out var iter, token);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<ulong>(iter);
+        }
+
+        [DllImport("cs_iter.dll")]
+        private static extern void get_ticking_stream(out RustFFIStream iter, ulong count);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Creates a `Stream&lt;Item=u64&gt;` counting up to `count`, with an awaited
+        /// pause before each number
+        /// </summary>
+        public static RustStream<ulong> GetTickingStream(ulong count)
+        {
+            get_ticking_stream(out var iter, count);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustStream<ulong>(iter);
+        }
+
+        [DllImport("cs_iter.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        private static extern bool get_range_iterator(out RustFFIIterator iter, ulong start, ulong end, ulong step);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Counts from `start` up to `end` in steps of `step`, written with
+        /// `#[export_iterator]` instead of by hand. A `step` of 0 panics, which
+        /// `C#` finds out about through the last error
+        /// </summary>
+        public static RustIter<ulong> GetRangeIterator(ulong start, ulong end, ulong step)
+        {
+            if (!get_range_iterator(out var iter, start, end, step))
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<ulong>(iter);
+        }
+
+        [DllImport("cs_iter.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        private static extern bool get_word_iterator(out RustFFIIterator iter, byte[] text, UIntPtr textLen);
+        /// <summary>
+        /// An example function:
+        ///
+        /// Splits a string from `C#` into words, ready to be turned back into
+        /// `C#` strings
+        /// </summary>
+        public static RustIter<FfiUtf16> GetWordIterator(string text)
+        {
+            var textBytes = Encoding.UTF8.GetBytes(text);
+            if (!get_word_iterator(out var iter, textBytes, (UIntPtr)textBytes.Length))
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<FfiUtf16>(iter);
+        }
+
+        /// <summary>
+        /// An example function:
+        ///
+        /// Adds up a sequence of numbers that `C#` owns, wrapping on overflow
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern ulong sum_foreign(RustForeignIterator iter);
+
+        /// <summary>
+        /// An example function:
+        ///
+        /// Pushes the squares of `0..count` into a `C#` callback, stopping
+        /// whenever it says so
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern RustIterStatus push_squares(ulong count, RustSink sink);
+
+        /// <summary>
+        /// Creates a new token for `C#`, which has to be freed with
+        /// `cancellation_token_free`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern IntPtr cancellation_token_new();
+
+        /// <summary>
+        /// Triggers a token. Can be called from any thread, and does nothing
+        /// when given a null pointer.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void cancellation_token_cancel(IntPtr token);
+
+        /// <summary>
+        /// Whether a token was triggered. A null token never is.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool cancellation_token_is_cancelled(IntPtr token);
+
+        /// <summary>
+        /// Frees `C#`'s token. Iterators it was attached to keep working with
+        /// their own clones.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void cancellation_token_free(IntPtr token);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;u8&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_u8_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;u16&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_u16_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;u32&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_u32_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;u64&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_u64_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;usize&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_usize_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;i8&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_i8_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;i16&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_i16_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;i32&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_i32_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;i64&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_i64_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;isize&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_isize_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;f32&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_f32_free(FfiVec v);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;f64&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_vec_f64_free(FfiVec v);
+
+        /// <summary>
+        /// The code of the last error on this thread: the `IterStatus` the failing
+        /// call returned (or would have, for calls that don't return one), or
+        /// `IterStatus::Item` if nothing failed since the last successful
+        /// constructor call, or `destroy` of a `CSharpIteratorHandle`.
+        ///
+        /// Errors are recorded by every stock function that returns
+        /// `IterStatus::Panicked`, `IterStatus::Error` or
+        /// `IterStatus::InvalidHandle`, by `destroy` when the iterator panicked
+        /// while being dropped, and by constructors written with `export_into`,
+        /// like `get_iterator`. Nothing but those two clears it.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern RustIterStatus last_error_code();
+
+        /// <summary>
+        /// Copies the message of the last error on this thread into `buf` as
+        /// UTF-8, writing at most `len` bytes, with no nul terminator. Returns
+        /// the full length of the message in bytes, or 0 if there is none.
+        ///
+        /// To build an exception, call it once with a null `buf` to get the
+        /// length, allocate that many bytes, and call it again to fill them.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern unsafe UIntPtr last_error_message(byte* buf, UIntPtr len);
+
+        /// <summary>
+        /// Copies the layout of every `#[repr(C)]` type `C#` sees into `buf`,
+        /// writing at most `len` entries, and returns how many there are in
+        /// total. A host can check these against its own definitions at startup,
+        /// instead of finding out through corrupted items.
+        ///
+        /// Like `last_error_message`, call it once with a null `buf` to get the
+        /// count, allocate that many entries, and call it again to fill them.
+        /// The names point into static memory, and never have to be freed.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern unsafe UIntPtr abi_layout_info(RustLayout* buf, UIntPtr len);
+
+        /// <summary>
+        /// Frees an `FfiUtf8` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_utf8_free(FfiUtf8 s);
+
+        /// <summary>
+        /// Frees an `FfiUtf16` that was handed to `C#`.
+        /// </summary>
+        [DllImport("cs_iter.dll")]
+        public static extern void ffi_utf16_free(FfiUtf16 s);
+    }
+}
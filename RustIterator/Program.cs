@@ -8,61 +8,26 @@ using System.Runtime.InteropServices;
 namespace RustIterator
 {
     /// <summary>
-    /// Rust Vector, ready to be passed from an ffi into C# because of its identical memory model
+    /// Helpers for rust's FfiVec&lt;T&gt;, whose fields are generated
+    /// in Bindings.cs
     /// </summary>
     /// <example>
-    /// [DllImport("rust_lib.dll")]
-    /// private static extern void get_vec(out Vec RsVector);
-    /// 
-    /// var v = new Vec();
-    /// get_vec(out v);
+    /// foreach (var v in RustExports.GetIterator())
+    /// {
+    ///     var numbers = v.ToList&lt;ulong&gt;();
+    /// }
     /// </example>
-    [StructLayout(LayoutKind.Sequential)]
-    unsafe struct Vec
+    public unsafe partial struct FfiVec
     {
-        /// <summary>
-        /// The pointer to the data on the heap that the Vec&lt;T&gt; points to.
-        /// </summary>
-        /// <remarks>
-        /// This is in this place, because of the following chain of equivalences:
-        /// Vec&lt;T&gt;: https://doc.rust-lang.org/src/alloc/vec.rs.html#306-309
-        /// └buf: RawVec&lt;T&gt;: https://doc.rust-lang.org/1.26.0/src/alloc/raw_vec.rs.html#50-54
-        ///  └ptr: Unique&lt;T&gt;: https://doc.rust-lang.org/1.26.2/src/core/ptr.rs.html#2514-2522
-        ///   └pointer: NonZero&lt;*const T&gt;: https://doc.rust-lang.org/1.22.1/src/core/nonzero.rs.html#66
-        ///    └.0: *const T
-        /// </remarks>
-        public IntPtr ptr;
-        /// <summary>
-        /// The full capacity of the Vec&lt;T&gt;; unused because not all of it is guaranteed to be initialized
-        /// </summary>
-        /// <remarks>
-        /// This is in this place, because of the following chain of equivalences:
-        /// Vec&lt;T&gt;: https://doc.rust-lang.org/src/alloc/vec.rs.html#306-309
-        /// └buf: RawVec&lt;T&gt;: https://doc.rust-lang.org/1.26.0/src/alloc/raw_vec.rs.html#50-54
-        ///  └cap: usize
-        /// </remarks>
-        public ulong capacity;
-        /// <summary>
-        /// The length of the used memory of the Vec&lt;T&gt;'s 
-        /// currently allocated memory; therefore guaranteed 
-        /// to be initialized
-        /// </summary>
-        /// <remarks>
-        /// This is in this place, because of the following chain of equivalences:
-        /// Vec&lt;T&gt;: https://doc.rust-lang.org/src/alloc/vec.rs.html#306-309
-        /// └len: usize
-        /// </remarks>
-        public ulong size;
-
         /// <summary>
         /// Because we can't infer the type for this, we just pretty-print the pointer, capacity and length
         /// </summary>
         /// <returns>
-        /// A pretty-printed version of rust's Vec&lt;T&gt; without knowledge of &lt;T&gt;
+        /// A pretty-printed version of rust's FfiVec&lt;T&gt; without knowledge of &lt;T&gt;
         /// </returns>
         public override string ToString()
         {
-            return "ptr: " + ptr.ToInt64().ToString("x") + "\ncap: " + capacity + "\nsize: " + size;
+            return "ptr: " + Ptr.ToInt64().ToString("x") + "\ncap: " + Capacity + "\nsize: " + Len;
         }
         /// <summary>
         /// Using a caller-provided type, creates a List&lt;T&gt; using some Marshalling
@@ -76,10 +41,11 @@ namespace RustIterator
         /// </returns>
         public List<T> ToList<T>() where T : struct
         {
+            var size = (ulong)Len;
             /// First allocate a byte array because Marshal.Copy doesn't have a pointer to pointer variant
             var arr = new byte[size * (ulong)Marshal.SizeOf<T>()];
             /// Copy the bytes from the rust vec to the buffer we just created
-            Marshal.Copy(ptr, arr, 0, arr.Length);
+            Marshal.Copy(Ptr, arr, 0, arr.Length);
             /// Prepare an array of &lt;T&gt;
             var narr = new T[size];
             /// Pin the new array so that we can write to it
@@ -92,24 +58,29 @@ namespace RustIterator
             return narr.ToList();
         }
     }
-    class Program
+    /// <summary>
+    /// Helpers for rust's FfiUtf16, a UTF-16 string that can be read
+    /// straight into a C# string
+    /// </summary>
+    public unsafe partial struct FfiUtf16
     {
         /// <summary>
-        /// Our external function that gets us an iterator
+        /// Copies the chars into a C# string
         /// </summary>
-        /// <param name="iter">
-        /// A reference to an uninitialized iterator
-        /// </param>
-        [DllImport("cs_iter.dll")]
-        private static extern void get_iterator(out RustFFIIterator iter);
+        public override string ToString()
+        {
+            return new string((char*)Ptr, 0, (int)(ulong)Len);
+        }
+    }
+    class Program
+    {
         static void Main(string[] args)
         {
-            /// Uninitialized iterator
-            var iter = new RustFFIIterator();
-            /// Initialize it in rust
-            get_iterator(out iter);
-            /// Create our special iterator for rust objects
-            var i = new RustIter<Vec>(iter);
+            /// Make sure our structs match rust's before using any
+            RustAbi.Verify();
+            /// Get our special iterator for rust objects, the
+            /// functions that make them are generated from rust
+            var i = RustExports.GetIterator();
             /// Loop
             foreach (var b in i)
             {
@@ -120,6 +91,81 @@ namespace RustIterator
                     Console.Write(c + " ");
                 }
                 Console.WriteLine();
+                /// We own every item rust gives us, so give it back
+                i.Release(b);
+            }
+            /// Strings work the same way
+            var lines = RustExports.GetStringIterator();
+            foreach (var line in lines)
+            {
+                Console.WriteLine(line.ToString());
+                lines.Release(line);
+            }
+            /// And rust can iterate over our sequences too
+            var numbers = new HostSequence<ulong>(Enumerable.Range(1, 100).Select(x => (ulong)x));
+            Console.WriteLine("Sum: " + RustExports.sum_foreign(numbers.Raw));
+            GC.KeepAlive(numbers);
+            /// Or push items into a callback of ours, which is cheaper
+            /// than asking for them one by one
+            var squares = new List<ulong>();
+            HostPush push;
+            unsafe
+            {
+                push = (context, item) =>
+                {
+                    squares.Add(*(ulong*)item);
+                    return squares.Count < 10;
+                };
+            }
+            var sink = new RustSink { Context = IntPtr.Zero, Push = Marshal.GetFunctionPointerForDelegate(push) };
+            RustExports.push_squares(100, sink);
+            GC.KeepAlive(push);
+            Console.WriteLine("Squares: " + string.Join(" ", squares));
+            /// Functions exported with #[export_iterator] say whether
+            /// they worked, so bad arguments don't go unnoticed
+            try
+            {
+                RustExports.GetRangeIterator(0, 100, 0);
+            }
+            catch (InvalidOperationException e)
+            {
+                Console.WriteLine(e.Message);
+            }
+            Console.WriteLine("Range: " + string.Join(" ", RustExports.GetRangeIterator(0, 100, 7)));
+            var words = RustExports.GetWordIterator("words from C# über rust");
+            foreach (var word in words)
+            {
+                Console.WriteLine(word.ToString());
+                words.Release(word);
+            }
+            /// Iterators that would go on forever can be stopped
+            /// from another thread
+            using (var token = new RustCancellationToken())
+            {
+                var ticks = RustExports.GetSlowIterator(token.Raw);
+                Task.Delay(1000).ContinueWith(_ => token.Cancel());
+                try
+                {
+                    foreach (var tick in ticks)
+                    {
+                        Console.Write(tick + " ");
+                    }
+                }
+                catch (OperationCanceledException)
+                {
+                    Console.WriteLine("cancelled");
+                }
+            }
+            /// Streams are awaited one item at a time instead. Main
+            /// isn't async so it blocks on each one, but an async caller
+            /// wouldn't tie up a thread while rust waits
+            using (var stream = RustExports.GetTickingStream(5))
+            {
+                while (stream.MoveNextAsync().GetAwaiter().GetResult())
+                {
+                    Console.Write(stream.Current + " ");
+                }
+                Console.WriteLine();
             }
             Console.ReadKey();
         }
@@ -9,53 +9,159 @@ using System.Runtime.InteropServices;
 namespace RustIterator
 {
     /// <summary>
-    /// The iterator that rust will be sending us, the core of this example.
-    /// This is a specialized struct in both rust AND C#
+    /// A rust CancellationToken, which stops the iterators it's
+    /// attached to from any thread
     /// </summary>
-    [StructLayout(LayoutKind.Sequential)]
-    public struct RustFFIIterator
+    public sealed class RustCancellationToken : IDisposable
     {
         /// <summary>
-        /// A pointer to a function that contains
-        /// a call to the internal iterator's next() function
-        /// </summary>
-        /// <remarks>
-        /// Equivalent to a RustIteratorNext. If there is another
-        /// value, then it will initialize the reference, otherwise
-        /// it will destroy the iterator and return false
-        /// </remarks>
-        public IntPtr Next;
-        /// <summary>
-        /// A pointer to a pointer to our iterator. 
-        /// </summary>
-        /// <remarks>
-        /// Needs two layers of indirection because rust treats 
-        /// references to trait objects as a pair of pointers,
-        /// so it's easier to just pass a pointer to the pair.
-        /// This shouldn't be touched in C#
-        /// </remarks>
-        public IntPtr Iterator;
+        /// The pointer to pass to rust functions that take a token
+        /// </summary>
+        public IntPtr Raw { get; private set; }
+
+        public RustCancellationToken()
+        {
+            Raw = RustExports.cancellation_token_new();
+        }
+
+        public void Cancel() => RustExports.cancellation_token_cancel(Raw);
+
+        public bool IsCancelled => RustExports.cancellation_token_is_cancelled(Raw);
+
+        /// <summary>
+        /// Frees our token, iterators it's attached to keep their own
+        /// </summary>
+        public void Dispose()
+        {
+            RustExports.cancellation_token_free(Raw);
+            Raw = IntPtr.Zero;
+        }
     }
     /// <summary>
-    /// The model for our iterator's next function, 
-    /// <seealso cref="RustFFIIterator.Next"/> for a pointer to it
+    /// Why the last failing rust call on this thread failed
     /// </summary>
-    /// <param name="iter">
-    /// A pointer to the iterator itself.
-    /// <see cref="RustFFIIterator.Iterator"/>
-    /// </param>
-    /// <param name="a">
-    /// A pointer to the data that it will initialize. 
-    /// This uses a bit of pointer magic
-    /// </param>
-    /// <returns>
-    /// If there are any more objects to return.
-    /// </returns>
-    /// <remarks>
-    /// If this returns false, don't call it again, 
-    /// as the rust iterator is now dropped
-    /// </remarks>
-    public unsafe delegate bool RustIteratorNext(IntPtr iter, void* a);
+    public static class RustLastError
+    {
+        /// <summary>
+        /// The status the failing call returned, or
+        /// <see cref="RustIterStatus.Item"/> if there wasn't one
+        /// </summary>
+        public static RustIterStatus Code => RustExports.last_error_code();
+
+        /// <summary>
+        /// What went wrong, or an empty string
+        /// </summary>
+        public static string Message
+        {
+            get
+            {
+                unsafe
+                {
+                    /// Ask for the length first, then for the message
+                    var len = (int)RustExports.last_error_message(null, UIntPtr.Zero);
+                    var buf = new byte[len];
+                    fixed (byte* p = buf)
+                    {
+                        RustExports.last_error_message(p, (UIntPtr)len);
+                    }
+                    return Encoding.UTF8.GetString(buf);
+                }
+            }
+        }
+
+        /// <summary>
+        /// Builds an exception out of the last error
+        /// </summary>
+        public static Exception ToException()
+        {
+            return new InvalidOperationException("Rust call failed (" + Code + "): " + Message);
+        }
+    }
+    /// <summary>
+    /// Checks that our structs line up with rust's, so that a mismatch
+    /// fails at startup instead of corrupting items later on
+    /// </summary>
+    public static class RustAbi
+    {
+        /// <summary>
+        /// Every entry rust has, a type followed by its fields
+        /// </summary>
+        public static RustLayout[] Layouts
+        {
+            get
+            {
+                unsafe
+                {
+                    /// Ask for the count first, then for the entries
+                    var len = (int)RustExports.abi_layout_info(null, UIntPtr.Zero);
+                    var buf = new RustLayout[len];
+                    fixed (RustLayout* p = buf)
+                    {
+                        RustExports.abi_layout_info(p, (UIntPtr)len);
+                    }
+                    return buf;
+                }
+            }
+        }
+
+        /// <summary>
+        /// Checks the structs in this library, and throws if
+        /// any of them don't match
+        /// </summary>
+        public static void Verify()
+        {
+            Check(typeof(RustSizeHint), "FfiSizeHint", "Lower", "Upper", "HasUpper", "Exact");
+            Check(typeof(RustFFIIterator), "CSharpIteratorOut<T>", "Next", "Iterator", "Destroy", "Message", "ReleaseItem", "SizeHint", "NextChunk");
+            Check(typeof(RustForeignIterator), "ForeignIterator<T>", "Context", "Next", "Release");
+            Check(typeof(RustSink), "CSharpSink<T>", "Context", "Push");
+            Check(typeof(RustFFIStream), "CSharpStreamOut<T>", "RequestNext", "Stream", "Destroy", "Message", "ReleaseItem");
+            Check(typeof(RustCompletion), "CSharpCompletion", "Context", "Complete");
+            Check(typeof(RustLayout), "FfiLayout", "Type", "Field", "Size", "Align", "Offset");
+            Check(typeof(FfiVec), "FfiVec<T>", "Ptr", "Len", "Capacity");
+            Check(typeof(FfiUtf8), "FfiString<C>", "Ptr", "Len");
+            Check(typeof(FfiUtf16), "FfiString<C>", "Ptr", "Len");
+        }
+
+        /// <summary>
+        /// Checks that a struct of ours has the size of the rust type,
+        /// and that its fields are where rust's fields are
+        /// </summary>
+        /// <param name="type">
+        /// Our struct
+        /// </param>
+        /// <param name="rustType">
+        /// The rust type, like CSharpIteratorOut&lt;T&gt;
+        /// </param>
+        /// <param name="fields">
+        /// Our fields, in the order rust declares them
+        /// </param>
+        public static void Check(Type type, string rustType, params string[] fields)
+        {
+            var entries = Layouts.Where(l => Marshal.PtrToStringAnsi(l.Type) == rustType).ToArray();
+            if (entries.Length == 0)
+            {
+                throw new InvalidOperationException("Rust doesn't know about " + rustType);
+            }
+            var size = (long)(ulong)entries[0].Size;
+            if (Marshal.SizeOf(type) != size)
+            {
+                throw new InvalidOperationException(type.Name + " is " + Marshal.SizeOf(type) + " bytes, but " + rustType + " is " + size);
+            }
+            if (entries.Length - 1 != fields.Length)
+            {
+                throw new InvalidOperationException(type.Name + " has " + fields.Length + " fields, but " + rustType + " has " + (entries.Length - 1));
+            }
+            for (var i = 0; i < fields.Length; i++)
+            {
+                var rustField = Marshal.PtrToStringAnsi(entries[i + 1].Field);
+                var offset = (long)Marshal.OffsetOf(type, fields[i]);
+                if (offset != (long)(ulong)entries[i + 1].Offset)
+                {
+                    throw new InvalidOperationException(type.Name + "." + fields[i] + " is at " + offset + ", but " + rustType + "." + rustField + " is at " + entries[i + 1].Offset);
+                }
+            }
+        }
+    }
     /// <summary>
     /// A smart manager for a rust iterator in C#. Allows
     /// for iteration just like an iterator would in rust.
@@ -64,12 +170,27 @@ namespace RustIterator
     /// The type that is being iterated over. Note that this 
     /// must be a sized struct that doesn't contain a class.
     /// </typeparam>
-    public class RustIter<T> : IEnumerable<T> where T: struct
+    /// <remarks>
+    /// Like the rust iterator behind it, this can only be
+    /// enumerated once: a second GetEnumerator throws instead
+    /// of freeing the rust iterator twice. One that's never
+    /// enumerated has to be disposed instead
+    /// </remarks>
+    public class RustIter<T> : IEnumerable<T>, IDisposable where T: struct
     {
         /// <summary>
         /// An internal copy of the iterator that rust sent over.
         /// </summary>
         private RustFFIIterator ffiiter;
+        /// <summary>
+        /// Whether an enumerator already owns the rust iterator,
+        /// since only one of them may free it
+        /// </summary>
+        private bool enumerated;
+        /// <summary>
+        /// Whether we freed the rust iterator ourselves
+        /// </summary>
+        private bool disposed;
 
         /// <summary>
         /// Constructor
@@ -82,17 +203,76 @@ namespace RustIterator
             ffiiter = d;
         }
 
+        /// <summary>
+        /// Asks rust how many items are left, which is handy
+        /// for sizing an array up front. Musn't be called after
+        /// the enumerator was disposed
+        /// </summary>
+        public RustSizeHint SizeHint()
+        {
+            var sizeHint = Marshal.GetDelegateForFunctionPointer<RustIteratorSizeHint>(ffiiter.SizeHint);
+            return sizeHint(ffiiter.Iterator);
+        }
+
+        /// <summary>
+        /// Hands an item back to rust so it can be freed
+        /// </summary>
+        /// <param name="item">
+        /// An item we got from this iterator, which musn't
+        /// be used after this
+        /// </param>
+        public void Release(T item)
+        {
+            var release = Marshal.GetDelegateForFunctionPointer<RustIteratorRelease>(ffiiter.ReleaseItem);
+            /// Pin a copy, since rust only needs the bits
+            var handle = GCHandle.Alloc(item, GCHandleType.Pinned);
+            unsafe
+            {
+                release((void*)handle.AddrOfPinnedObject());
+            }
+            handle.Free();
+        }
+
         #region IEnumerator
+        /// <summary>
+        /// Hands the rust iterator over to an enumerator, which frees
+        /// it once it's disposed. Rust iterators can't be restarted,
+        /// so this can only be called once
+        /// </summary>
         public IEnumerator<T> GetEnumerator()
         {
+            if (disposed)
+            {
+                throw new ObjectDisposedException(GetType().Name);
+            }
+            if (enumerated)
+            {
+                throw new InvalidOperationException("A rust iterator can only be enumerated once");
+            }
+            enumerated = true;
             return new REnumerator(ffiiter);
         }
 
         IEnumerator IEnumerable.GetEnumerator()
         {
-            return new REnumerator(ffiiter);
+            return GetEnumerator();
         }
         #endregion
+
+        /// <summary>
+        /// Frees the rust iterator, unless an enumerator took it
+        /// over, in which case disposing that one frees it
+        /// </summary>
+        public void Dispose()
+        {
+            if (enumerated || disposed)
+            {
+                return;
+            }
+            disposed = true;
+            var destroy = Marshal.GetDelegateForFunctionPointer<RustIteratorDestroy>(ffiiter.Destroy);
+            destroy(ffiiter.Iterator);
+        }
         /// <summary>
         /// Rust enumerator; takes care of marshalling stuff
         /// </summary>
@@ -105,10 +285,9 @@ namespace RustIterator
             /// used to keep a pinned object while iterating.
             /// </summary>
             private GCHandle handle;
-            /// When the iterator is done, it'll automatically 
-            /// destroy it leaving us with a dangling pointer,
-            /// so we have to track the state to not throw an
-            /// inter-ffi exception and a rust unwind
+            /// When the iterator is done there's no point in
+            /// asking rust for more, so we track the state to
+            /// skip the call
             private bool ended;
             /// <summary>
             /// The actual function that is called to get the
@@ -116,6 +295,15 @@ namespace RustIterator
             /// </summary>
             private RustIteratorNext next;
             /// <summary>
+            /// The function that frees the iterator once we're
+            /// disposed
+            /// </summary>
+            private RustIteratorDestroy destroy;
+            /// <summary>
+            /// The function that explains a panic
+            /// </summary>
+            private RustIteratorMessage message;
+            /// <summary>
             /// The pointer to the iterator, copied from the 
             /// <see cref="RustFFIIterator"/> that we recieve
             /// </summary>
@@ -131,12 +319,16 @@ namespace RustIterator
 
             public REnumerator(RustFFIIterator d)
             {
-                /// A default value
+                /// A slot for rust to write into, whatever is in it is never read
                 var data = default(T);
                 /// We pin the value
                 handle = GCHandle.Alloc(data, GCHandleType.Pinned);
                 /// We initialize the next() function
                 next = Marshal.GetDelegateForFunctionPointer<RustIteratorNext>(d.Next);
+                /// And the destroy() function
+                destroy = Marshal.GetDelegateForFunctionPointer<RustIteratorDestroy>(d.Destroy);
+                /// And the message() function
+                message = Marshal.GetDelegateForFunctionPointer<RustIteratorMessage>(d.Message);
                 /// Store the iterator
                 iterPtr = d.Iterator;
                 /// We still haven't ended, since this is the constructor
@@ -145,8 +337,17 @@ namespace RustIterator
 
             public void Dispose()
             {
-                /// Unpin this' current value
-                handle.Free();
+                /// Unpin this' current value, unless we already did
+                if (handle.IsAllocated)
+                {
+                    handle.Free();
+                }
+                /// Free the rust iterator, even if we stopped early
+                if (iterPtr != IntPtr.Zero)
+                {
+                    destroy(iterPtr);
+                    iterPtr = IntPtr.Zero;
+                }
             }
 
             public bool MoveNext()
@@ -154,20 +355,55 @@ namespace RustIterator
                 /// Don't try to call a function on a dangling pointer.
                 if (!ended)
                 {
-                    bool worked;
+                    RustIterStatus status;
                     unsafe
                     {
-                        worked = next(iterPtr, (void*)handle.AddrOfPinnedObject());
+                        status = next(iterPtr, (void*)handle.AddrOfPinnedObject());
                     }
-                    if (!worked)
+                    switch (status)
                     {
-                        ended = true;
+                        case RustIterStatus.Item:
+                            return true;
+                        case RustIterStatus.Exhausted:
+                        case RustIterStatus.AlreadyFinished:
+                            ended = true;
+                            return false;
+                        case RustIterStatus.Panicked:
+                            ended = true;
+                            throw new InvalidOperationException("Rust iterator panicked: " + Message());
+                        case RustIterStatus.Cancelled:
+                            ended = true;
+                            throw new OperationCanceledException("Rust iterator was cancelled");
+                        case RustIterStatus.Error:
+                            /// Not the end, so MoveNext can be called
+                            /// again to skip past the bad item
+                            throw new InvalidOperationException("Rust iterator item failed: " + Message());
+                        default:
+                            ended = true;
+                            throw new InvalidOperationException("Rust iterator failed: " + status);
                     }
-                    return worked;
                 }
                 return !ended;
             }
 
+            /// <summary>
+            /// Asks rust why the iterator panicked, or why the
+            /// last item was an error
+            /// </summary>
+            private string Message()
+            {
+                unsafe
+                {
+                    var len = (int)message(iterPtr, null, UIntPtr.Zero);
+                    var buf = new byte[len];
+                    fixed (byte* p = buf)
+                    {
+                        message(iterPtr, p, (UIntPtr)len);
+                    }
+                    return Encoding.UTF8.GetString(buf);
+                }
+            }
+
             /// <summary>
             /// Nonfunctional because we can't reset a rust iterator
             /// </summary>
@@ -177,4 +413,220 @@ namespace RustIterator
             }
         }
     }
+    /// <summary>
+    /// The model for the callback rust calls to get the next item
+    /// from a C# sequence
+    /// </summary>
+    /// <param name="context">
+    /// <see cref="RustForeignIterator.Context"/>
+    /// </param>
+    /// <param name="slot">
+    /// Where to write the next item
+    /// </param>
+    /// <returns>
+    /// Whether an item was written
+    /// </returns>
+    public unsafe delegate bool HostNext(IntPtr context, void* slot);
+    /// <summary>
+    /// The model for the callback rust calls once it's done
+    /// with a C# sequence
+    /// </summary>
+    /// <param name="context">
+    /// <see cref="RustForeignIterator.Context"/>
+    /// </param>
+    public delegate void HostRelease(IntPtr context);
+    /// <summary>
+    /// Lends a C# sequence to rust, where it's a ForeignIterator&lt;T&gt;
+    /// </summary>
+    /// <typeparam name="T">
+    /// The type that is being iterated over, with the same
+    /// restrictions as <see cref="RustIter{T}"/>
+    /// </typeparam>
+    public class HostSequence<T> where T : struct
+    {
+        /// <summary>
+        /// The enumerator rust is reading from
+        /// </summary>
+        private readonly IEnumerator<T> enumerator;
+        /// <summary>
+        /// The callbacks, kept here so they aren't collected
+        /// while rust can still call them
+        /// </summary>
+        private readonly HostNext next;
+        private readonly HostRelease release;
+
+        public HostSequence(IEnumerable<T> sequence)
+        {
+            enumerator = sequence.GetEnumerator();
+            unsafe
+            {
+                next = (context, slot) =>
+                {
+                    if (!enumerator.MoveNext())
+                    {
+                        return false;
+                    }
+                    Marshal.StructureToPtr(enumerator.Current, (IntPtr)slot, false);
+                    return true;
+                };
+            }
+            release = context => enumerator.Dispose();
+        }
+
+        /// <summary>
+        /// The struct to pass to rust. This object has to be kept
+        /// alive until the rust call returns
+        /// </summary>
+        public RustForeignIterator Raw => new RustForeignIterator
+        {
+            Context = IntPtr.Zero,
+            Next = Marshal.GetFunctionPointerForDelegate(next),
+            Release = Marshal.GetFunctionPointerForDelegate(release),
+        };
+    }
+    /// <summary>
+    /// The model for the callback rust pushes each item into
+    /// </summary>
+    /// <param name="context">
+    /// <see cref="RustSink.Context"/>
+    /// </param>
+    /// <param name="item">
+    /// The item, only valid until this returns
+    /// </param>
+    /// <returns>
+    /// Whether rust should keep going
+    /// </returns>
+    public unsafe delegate bool HostPush(IntPtr context, void* item);
+    /// <summary>
+    /// The model for the function that asks a stream for its next item
+    /// </summary>
+    /// <param name="stream">
+    /// <see cref="RustFFIStream.Stream"/>
+    /// </param>
+    /// <param name="slot">
+    /// Where to write the item, which has to stay put until
+    /// the request completes
+    /// </param>
+    /// <param name="completion">
+    /// Called exactly once, maybe before this even returns
+    /// and maybe from another thread
+    /// </param>
+    public unsafe delegate void RustStreamRequestNext(IntPtr stream, void* slot, RustCompletion completion);
+    /// <summary>
+    /// The model for the callback a request completes through
+    /// </summary>
+    /// <param name="context">
+    /// <see cref="RustCompletion.Context"/>
+    /// </param>
+    /// <param name="status">
+    /// <see cref="RustIterStatus.Item"/> if the slot holds an item now
+    /// </param>
+    public delegate void HostComplete(IntPtr context, RustIterStatus status);
+    /// <summary>
+    /// A rust stream, read one awaited item at a time. This has the
+    /// same shape as an IAsyncEnumerator, so it's easy to wrap in one
+    /// where that's available
+    /// </summary>
+    /// <typeparam name="T">
+    /// The type that is being iterated over, with the same
+    /// restrictions as <see cref="RustIter{T}"/>
+    /// </typeparam>
+    public class RustStream<T> : IDisposable where T : struct
+    {
+        private RustFFIStream ffistream;
+        /// <summary>
+        /// The pinned slot rust writes items into
+        /// </summary>
+        private GCHandle handle;
+        private readonly RustStreamRequestNext requestNext;
+        private readonly RustIteratorDestroy destroy;
+        private readonly RustIteratorMessage message;
+        /// <summary>
+        /// Kept here so it isn't collected while rust can still call it
+        /// </summary>
+        private readonly HostComplete complete;
+        /// <summary>
+        /// The request that's waiting, if any
+        /// </summary>
+        private TaskCompletionSource<RustIterStatus> pending;
+        private bool ended;
+
+        public RustStream(RustFFIStream d)
+        {
+            ffistream = d;
+            handle = GCHandle.Alloc(default(T), GCHandleType.Pinned);
+            requestNext = Marshal.GetDelegateForFunctionPointer<RustStreamRequestNext>(d.RequestNext);
+            destroy = Marshal.GetDelegateForFunctionPointer<RustIteratorDestroy>(d.Destroy);
+            message = Marshal.GetDelegateForFunctionPointer<RustIteratorMessage>(d.Message);
+            complete = (context, status) => pending.SetResult(status);
+        }
+
+        public T Current => (T)handle.Target;
+
+        /// <summary>
+        /// Waits for the next item, the same as MoveNext does
+        /// for a <see cref="RustIter{T}"/>
+        /// </summary>
+        public async Task<bool> MoveNextAsync()
+        {
+            if (ended)
+            {
+                return false;
+            }
+            /// Rust may complete from inside RequestNext, so don't
+            /// run whoever awaits us on top of that
+            pending = new TaskCompletionSource<RustIterStatus>(TaskCreationOptions.RunContinuationsAsynchronously);
+            var completion = new RustCompletion
+            {
+                Context = IntPtr.Zero,
+                Complete = Marshal.GetFunctionPointerForDelegate(complete),
+            };
+            unsafe
+            {
+                requestNext(ffistream.Stream, (void*)handle.AddrOfPinnedObject(), completion);
+            }
+            var status = await pending.Task;
+            switch (status)
+            {
+                case RustIterStatus.Item:
+                    return true;
+                case RustIterStatus.Exhausted:
+                case RustIterStatus.AlreadyFinished:
+                    ended = true;
+                    return false;
+                case RustIterStatus.Panicked:
+                    ended = true;
+                    throw new InvalidOperationException("Rust stream panicked: " + Message());
+                default:
+                    ended = true;
+                    throw new InvalidOperationException("Rust stream failed: " + status);
+            }
+        }
+
+        private string Message()
+        {
+            unsafe
+            {
+                var len = (int)message(ffistream.Stream, null, UIntPtr.Zero);
+                var buf = new byte[len];
+                fixed (byte* p = buf)
+                {
+                    message(ffistream.Stream, p, (UIntPtr)len);
+                }
+                return Encoding.UTF8.GetString(buf);
+            }
+        }
+
+        public void Dispose()
+        {
+            /// Free the stream first, since that completes a request
+            /// that's still writing into our slot
+            if (ffistream.Stream != IntPtr.Zero)
+            {
+                destroy(ffistream.Stream);
+                ffistream.Stream = IntPtr.Zero;
+            }
+            handle.Free();
+        }
+    }
 }
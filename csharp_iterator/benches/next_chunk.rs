@@ -0,0 +1,66 @@
+//! Compares pulling items through `internal_iter` one at a time against
+//! pulling them through `next_chunk`, the way `C#` would. The function
+//! pointers go through `black_box` so they can't be inlined, since `C#`
+//! can't inline them either.
+//!
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use cs_iter::{CSharpIteratorOut, IterStatus};
+
+const ITEMS: u32 = 20_000_000;
+const CHUNK: usize = 256;
+
+fn single() -> Duration {
+    let it = CSharpIteratorOut::form(0..ITEMS);
+    let next = black_box(it.internal_iter);
+    let mut slot = 0u32;
+    let mut sum = 0u64;
+    let start = Instant::now();
+    unsafe {
+        while next(it.pointer, &mut slot) == IterStatus::Item {
+            sum += black_box(slot) as u64;
+        }
+    }
+    let elapsed = start.elapsed();
+    unsafe { (it.destroy)(it.pointer) };
+    assert_eq!(sum, (ITEMS as u64 - 1) * ITEMS as u64 / 2);
+    elapsed
+}
+
+fn chunked() -> Duration {
+    let it = CSharpIteratorOut::form(0..ITEMS);
+    let next_chunk = black_box(it.next_chunk);
+    let mut buf = [MaybeUninit::<u32>::uninit(); CHUNK];
+    let mut status = IterStatus::Item;
+    let mut sum = 0u64;
+    let start = Instant::now();
+    unsafe {
+        while status == IterStatus::Item {
+            let written = next_chunk(it.pointer, buf.as_mut_ptr() as *mut u32, CHUNK, &mut status);
+            for x in &buf[..written] {
+                sum += black_box(x.assume_init()) as u64;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    unsafe { (it.destroy)(it.pointer) };
+    assert_eq!(sum, (ITEMS as u64 - 1) * ITEMS as u64 / 2);
+    elapsed
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let per_second = ITEMS as f64 / elapsed.as_secs_f64();
+    println!("{:<24} {:>10.2?} {:>8.1} M items/s", name, elapsed, per_second / 1e6);
+}
+
+fn main() {
+    // Warm up both paths before measuring
+    single();
+    chunked();
+    report("internal_iter", single());
+    report(&format!("next_chunk ({})", CHUNK), chunked());
+}
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{parse_quote, Attribute, Fields, Item, ItemEnum, ItemFn, ItemStruct, ReturnType, Type};
+
+use crate::{doc_lines, exported_fns, parse_sources, generic_arg, has_attr, is_repr_c, iterator_item, last_ident, typed_arg};
+
+/// Generates a C header for every function the crate exports, along with
+/// every type they use, from the source of each of the crate's files
+/// (see `crate_sources`).
+///
+/// Generic `#[repr(C)]` structs are written out once per type they're
+/// used with, so `CSharpIteratorOut<u64>` becomes `CSharpIteratorOut_u64`.
+/// Types that aren't `#[repr(C)]`, like `CancellationToken`, are only
+/// declared, since C only ever sees pointers to them.
+pub fn generate_header(sources: &[String]) -> syn::Result<String> {
+    let files = parse_sources(sources)?;
+    let mut header = Header::default();
+    for file in &files {
+        for item in &file.items {
+            match item {
+                Item::Struct(item) => {
+                    header.structs.insert(item.ident.to_string(), item);
+                }
+                Item::Enum(item) => {
+                    header.enums.insert(item.ident.to_string(), item);
+                }
+                Item::Type(item) => {
+                    header.aliases.insert(item.ident.to_string(), &item.ty);
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut functions = Vec::new();
+    for func in exported_fns(&files)? {
+        if has_attr(&func.attrs, "export_iterator") {
+            functions.push(header.export_iterator(&func)?);
+        } else {
+            functions.push(header.function(&func)?);
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("/* Generated by cs_iter_bindgen from the rust source. Don't edit this\n");
+    out.push_str(" * by hand, run cs_iter_bindgen --header again instead. */\n");
+    out.push_str("#ifndef CS_ITER_H\n#define CS_ITER_H\n\n");
+    out.push_str("#include <stdbool.h>\n#include <stdint.h>\n\n");
+    out.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+    out.push_str(&header.types);
+    for function in functions {
+        out.push_str(&function);
+        out.push('\n');
+    }
+    out.push_str("#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+    Ok(out)
+}
+
+/// What the generic parameters of the struct being written out stand for
+type Substitutions = HashMap<String, Type>;
+
+#[derive(Default)]
+struct Header<'a> {
+    structs: HashMap<String, &'a ItemStruct>,
+    enums: HashMap<String, &'a ItemEnum>,
+    aliases: HashMap<String, &'a Type>,
+    /// The C names of the types already written out
+    written: HashSet<String>,
+    /// Every type written out so far, each after the ones it uses
+    types: String,
+}
+
+/// Writes a doc comment as a C comment
+fn comment(lines: &[String]) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut comment = "/**\n".to_string();
+    for line in lines {
+        comment.push_str(format!(" * {}", line).trim_end());
+        comment.push('\n');
+    }
+    comment.push_str(" */\n");
+    comment
+}
+
+/// `CamelCase` to `SCREAMING_SNAKE_CASE`
+fn screaming_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            out.push('_');
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
+
+/// The part of a C name that stands for a rust type, like `FfiVec_usize`
+/// for `FfiVec<usize>`
+fn mangle(ty: &Type) -> String {
+    match ty {
+        Type::Ptr(ptr) => format!("ptr_{}", mangle(&ptr.elem)),
+        ty => match last_ident(ty) {
+            Some(segment) => {
+                let mut name = segment.ident.to_string();
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::Type(arg) = arg {
+                            name.push('_');
+                            name.push_str(&mangle(arg));
+                        }
+                    }
+                }
+                name
+            }
+            None => "unknown".to_string(),
+        },
+    }
+}
+
+/// Replaces the generic parameters in `ty` with what they stand for
+fn substitute(ty: &Type, subs: &Substitutions) -> Type {
+    let mut ty = ty.clone();
+    match &mut ty {
+        Type::Path(path) if path.qself.is_none() => {
+            if let Some(ident) = path.path.get_ident() {
+                if let Some(sub) = subs.get(&ident.to_string()) {
+                    return sub.clone();
+                }
+            }
+            for segment in &mut path.path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    for arg in &mut args.args {
+                        if let syn::GenericArgument::Type(arg) = arg {
+                            *arg = substitute(arg, subs);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Ptr(ptr) => *ptr.elem = substitute(&ptr.elem, subs),
+        Type::BareFn(func) => {
+            for arg in &mut func.inputs {
+                arg.ty = substitute(&arg.ty, subs);
+            }
+            if let ReturnType::Type(_, ret) = &mut func.output {
+                **ret = substitute(ret, subs);
+            }
+        }
+        _ => {}
+    }
+    ty
+}
+
+impl<'a> Header<'a> {
+    /// The C type of `ty`, writing out whatever it needs first
+    fn c_type(&mut self, ty: &Type) -> syn::Result<String> {
+        match ty {
+            Type::Ptr(ptr) => {
+                let elem = self.c_type(&ptr.elem)?;
+                match ptr.const_token {
+                    Some(_) => Ok(format!("const {} *", elem)),
+                    None => Ok(format!("{} *", elem)),
+                }
+            }
+            Type::Tuple(tuple) if tuple.elems.is_empty() => Ok("void".to_string()),
+            ty => {
+                let segment = match last_ident(ty) {
+                    Some(segment) => segment,
+                    None => return Err(syn::Error::new(ty.span(), "there's no C type for this")),
+                };
+                let name = segment.ident.to_string();
+                let primitive = match name.as_str() {
+                    "u8" => "uint8_t",
+                    "u16" => "uint16_t",
+                    "u32" => "uint32_t",
+                    "u64" => "uint64_t",
+                    "usize" => "uintptr_t",
+                    "i8" => "int8_t",
+                    "i16" => "int16_t",
+                    "i32" => "int32_t",
+                    "i64" => "int64_t",
+                    "isize" => "intptr_t",
+                    "f32" => "float",
+                    "f64" => "double",
+                    "bool" => "bool",
+                    "c_void" => "void",
+                    _ => "",
+                };
+                if !primitive.is_empty() {
+                    return Ok(primitive.to_string());
+                }
+                if let Some(alias) = self.aliases.get(&name).copied() {
+                    let target = self.c_type(alias)?;
+                    if self.written.insert(name.clone()) {
+                        self.types.push_str(&format!("typedef {} {};\n\n", target, name));
+                    }
+                    return Ok(name);
+                }
+                if let Some(item) = self.enums.get(&name).copied() {
+                    self.write_enum(item)?;
+                    return Ok(name);
+                }
+                if let Some(item) = self.structs.get(&name).copied() {
+                    return self.write_struct(item, ty);
+                }
+                Err(syn::Error::new(ty.span(), format!("there's no C type for `{}`", name)))
+            }
+        }
+    }
+
+    /// Declares `name` as a `ty`, which is only different from
+    /// `c_type` for function pointers
+    fn declare(&mut self, ty: &Type, name: &str) -> syn::Result<String> {
+        // Nullable function pointers are just function pointers in C
+        if let Some(segment) = last_ident(ty) {
+            if segment.ident == "Option" {
+                if let Some(inner @ Type::BareFn(_)) = generic_arg(segment) {
+                    return self.declare(inner, name);
+                }
+            }
+        }
+        if let Type::BareFn(func) = ty {
+            let mut params = Vec::new();
+            for arg in &func.inputs {
+                let name = arg.name.as_ref().map(|(name, _)| name.to_string()).unwrap_or_default();
+                params.push(self.declare(&arg.ty, &name)?);
+            }
+            if params.is_empty() {
+                params.push("void".to_string());
+            }
+            let ret = self.return_type(&func.output)?;
+            return Ok(format!("{} (*{})({})", ret, name, params.join(", ")));
+        }
+        let c = self.c_type(ty)?;
+        Ok(match (c.ends_with('*'), name.is_empty()) {
+            (_, true) => c,
+            (true, false) => format!("{}{}", c, name),
+            (false, false) => format!("{} {}", c, name),
+        })
+    }
+
+    fn return_type(&mut self, output: &ReturnType) -> syn::Result<String> {
+        match output {
+            ReturnType::Default => Ok("void".to_string()),
+            ReturnType::Type(_, ty) => self.c_type(ty),
+        }
+    }
+
+    fn write_enum(&mut self, item: &ItemEnum) -> syn::Result<()> {
+        let name = item.ident.to_string();
+        if !self.written.insert(name.clone()) {
+            return Ok(());
+        }
+        let mut text = comment(&doc_lines(&item.attrs));
+        text.push_str(&format!("typedef enum {} {{\n", name));
+        for variant in &item.variants {
+            let value = match &variant.discriminant {
+                Some((_, value)) => value.to_token_stream().to_string(),
+                None => return Err(syn::Error::new(variant.span(), "every variant needs an explicit value")),
+            };
+            for line in comment(&doc_lines(&variant.attrs)).lines() {
+                text.push_str(&format!("    {}\n", line));
+            }
+            text.push_str(&format!("    {}_{} = {},\n", screaming_snake_case(&name), screaming_snake_case(&variant.ident.to_string()), value));
+        }
+        text.push_str(&format!("}} {};\n\n", name));
+        self.types.push_str(&text);
+        Ok(())
+    }
+
+    /// Writes out `item` for the generic arguments `ty` gives it,
+    /// returning its C name
+    fn write_struct(&mut self, item: &ItemStruct, ty: &Type) -> syn::Result<String> {
+        let name = mangle(ty);
+        if self.written.contains(&name) {
+            return Ok(name);
+        }
+        let repr_c = item.attrs.iter().any(is_repr_c);
+        let fields = match &item.fields {
+            Fields::Named(fields) if repr_c => &fields.named,
+            // Only ever behind a pointer
+            _ => {
+                self.written.insert(name.clone());
+                self.types.push_str(&format!("typedef struct {0} {0};\n\n", name));
+                return Ok(name);
+            }
+        };
+        let args: Vec<Type> = match last_ident(ty).map(|segment| &segment.arguments) {
+            Some(syn::PathArguments::AngleBracketed(args)) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    syn::GenericArgument::Type(arg) => Some(arg.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let subs: Substitutions = item.generics.type_params().map(|param| param.ident.to_string()).zip(args).collect();
+        if subs.len() != item.generics.type_params().count() {
+            return Err(syn::Error::new(ty.span(), format!("`{}` needs all of its generic arguments", item.ident)));
+        }
+        self.written.insert(name.clone());
+        // The fields' types are written out first
+        let mut body = String::new();
+        for field in fields {
+            let field_name = field.ident.as_ref().expect("named fields have names").to_string();
+            for line in comment(&doc_lines(&field.attrs)).lines() {
+                body.push_str(&format!("    {}\n", line));
+            }
+            body.push_str(&format!("    {};\n", self.declare(&substitute(&field.ty, &subs), &field_name)?));
+        }
+        let mut text = comment(&doc_lines(&item.attrs));
+        text.push_str(&format!("typedef struct {} {{\n{}}} {};\n\n", name, body, name));
+        self.types.push_str(&text);
+        Ok(name)
+    }
+
+    /// Declares a `#[no_mangle]` function
+    fn function(&mut self, func: &ItemFn) -> syn::Result<String> {
+        let mut params = Vec::new();
+        for input in &func.sig.inputs {
+            let (name, ty) = typed_arg(input)?;
+            params.push(self.declare(ty, &name)?);
+        }
+        self.prototype(&func.attrs, &func.sig.ident.to_string(), &func.sig.output, params)
+    }
+
+    /// Declares the function `#[export_iterator]` turns `func` into
+    fn export_iterator(&mut self, func: &ItemFn) -> syn::Result<String> {
+        let item = iterator_item(&func.sig.output)?;
+        let out: Type = parse_quote!(*mut CSharpIteratorOut<#item>);
+        let mut params = vec![self.declare(&out, "cs")?];
+        for input in &func.sig.inputs {
+            let (name, ty) = typed_arg(input)?;
+            let elem = match last_ident(ty) {
+                Some(segment) if segment.ident == "String" => Some("uint8_t".to_string()),
+                Some(segment) if segment.ident == "Vec" => match generic_arg(segment) {
+                    Some(elem) => Some(self.c_type(elem)?),
+                    None => return Err(syn::Error::new(ty.span(), "expected `Vec<T>`")),
+                },
+                _ => None,
+            };
+            match elem {
+                Some(elem) => {
+                    params.push(format!("const {} *{}_ptr", elem, name));
+                    params.push(format!("uintptr_t {}_len", name));
+                }
+                None => params.push(self.declare(ty, &name)?),
+            }
+        }
+        let ret: ReturnType = parse_quote!(-> bool);
+        self.prototype(&func.attrs, &func.sig.ident.to_string(), &ret, params)
+    }
+
+    fn prototype(&mut self, attrs: &[Attribute], name: &str, output: &ReturnType, params: Vec<String>) -> syn::Result<String> {
+        let params = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+        let ret = self.return_type(output)?;
+        let space = if ret.ends_with('*') { "" } else { " " };
+        Ok(format!("{}{}{}{}({});\n", comment(&doc_lines(attrs)), ret, space, name, params))
+    }
+}
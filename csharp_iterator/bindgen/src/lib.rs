@@ -0,0 +1,838 @@
+//! Generates the `C#` side of `cs_iter` from its source, so that the
+//! structs, delegates and `DllImport`s the host uses can't drift away from
+//! what rust actually exports.
+//!
+//! The source of every module (see `crate_sources`) is read for:
+//!
+//! - `IterStatus`, which becomes the `RustIterStatus` enum
+//! - `CSharpIteratorOut`, which becomes the `RustFFIIterator` struct, with
+//!   a delegate for each of its function pointers
+//! - the other `#[repr(C)]` structs the `C#` runtime is written against,
+//!   like `FfiSizeHint` and `ForeignIterator`, under the names in
+//!   `STRUCT_NAMES`
+//! - every function with `#[export_iterator]`, or with `#[no_mangle]` and
+//!   a `*mut CSharpIteratorOut<T>` as its first argument, which gets a
+//!   `DllImport` and a factory method returning a `RustIter<T>`
+//! - the same for a `*mut CSharpStreamOut<T>`, whose factory method
+//!   returns a `RustStream<T>`
+//! - every other `#[no_mangle]` function, including the ones
+//!   `ffi_vec_free!` expands to, which gets a public `DllImport`
+//!
+//! along with every `#[repr(C)]` struct those functions take or return,
+//! like `FfiVec<T>`. Generated structs are `partial`, so the `C#` side can
+//! add helpers to them without mirroring their fields.
+//!
+//! It can also generate a C header for the same exports, see
+//! `generate_header`.
+
+mod header;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, Attribute, Expr, Fields, FnArg, GenericArgument, Ident, Item, ItemEnum, ItemFn, ItemStruct, Lit, Meta, Pat, PathArguments, ReturnType, Token, Type,
+    TypeParamBound,
+};
+
+pub use header::generate_header;
+
+/// Where the generated code goes
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The namespace everything is generated in
+    pub namespace: String,
+    /// The library the `DllImport`s load
+    pub library: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            namespace: "RustIterator".to_string(),
+            library: "cs_iter.dll".to_string(),
+        }
+    }
+}
+
+/// The `C#` names of the structs the runtime in `RustIter.cs` is written
+/// against, which are generated whether or not an export uses them.
+/// Other structs keep their rust name
+const STRUCT_NAMES: &[(&str, &str)] = &[
+    ("FfiSizeHint", "RustSizeHint"),
+    ("CSharpIteratorOut", "RustFFIIterator"),
+    ("ForeignIterator", "RustForeignIterator"),
+    ("CSharpSink", "RustSink"),
+    ("CSharpStreamOut", "RustFFIStream"),
+    ("CSharpCompletion", "RustCompletion"),
+    ("FfiLayout", "RustLayout"),
+];
+
+/// The names the `C#` side has always used for some fields, where they
+/// aren't just rust's in PascalCase
+const FIELD_NAMES: &[(&str, &str, &str)] = &[
+    ("CSharpIteratorOut", "internal_iter", "Next"),
+    ("CSharpIteratorOut", "pointer", "Iterator"),
+    ("CSharpStreamOut", "pointer", "Stream"),
+    ("FfiLayout", "ty", "Type"),
+];
+
+/// The same for the delegates of `CSharpIteratorOut`'s function pointers
+const DELEGATE_NAMES: &[(&str, &str)] = &[("internal_iter", "RustIteratorNext"), ("release_item", "RustIteratorRelease")];
+
+/// Generates the `.cs` file for the crate whose source is `sources`,
+/// one string per file (see `crate_sources`)
+pub fn generate(sources: &[String], options: &Options) -> syn::Result<String> {
+    let files = parse_sources(sources)?;
+    let items: Vec<&Item> = files.iter().flat_map(|file| &file.items).collect();
+    let mut types = Types::new(&items);
+    let status = find_enum(&items, "IterStatus")?;
+    let iterator = find_struct(&items, "CSharpIteratorOut")?;
+    // The runtime can't do without these two, the rest are optional
+    find_struct(&items, "FfiSizeHint")?;
+    for (name, _) in STRUCT_NAMES {
+        if let Ok(item) = find_struct(&items, name) {
+            types.need(cs_struct_name(name), item);
+        }
+    }
+    let exports = exported_fns(&files)?.iter().map(|func| Export::from_fn(func)).collect::<syn::Result<Vec<_>>>()?;
+
+    // The exports are written first, since they decide which structs
+    // are needed
+    let mut exported = Writer { indent: 1, ..Writer::default() };
+    write_exports(&mut exported, &mut types, &exports, options)?;
+
+    let mut out = Writer::default();
+    out.line("// <auto-generated>");
+    out.line("// Generated by cs_iter_bindgen from the rust source. Don't edit this");
+    out.line("// by hand, run cs_iter_bindgen again instead.");
+    out.line("// </auto-generated>");
+    out.line("using System;");
+    out.line("using System.Runtime.InteropServices;");
+    out.line("using System.Text;");
+    out.line("");
+    out.line(&format!("namespace {}", options.namespace));
+    out.open();
+    write_enum(&mut out, status)?;
+    // Writing a struct can need another one, which is written after it
+    let mut written = 0;
+    while written < types.needed.len() {
+        let (name, item) = types.needed[written].clone();
+        types.write_struct(&mut out, item, &name)?;
+        if item.ident == "CSharpIteratorOut" {
+            types.write_delegates(&mut out, iterator)?;
+        }
+        written += 1;
+    }
+    out.text.push_str(&exported.text);
+    out.close();
+    Ok(out.text)
+}
+
+/// Parses the source of each of a crate's files
+fn parse_sources(sources: &[String]) -> syn::Result<Vec<syn::File>> {
+    sources.iter().map(|source| syn::parse_file(source)).collect()
+}
+
+/// Every function the crate exports, in the order they're defined in:
+/// the ones with `#[no_mangle]` or `#[export_iterator]`, and the ones
+/// `ffi_vec_free!` expands to, written out the way the macro would
+fn exported_fns(files: &[syn::File]) -> syn::Result<Vec<Cow<'_, ItemFn>>> {
+    let mut fns = Vec::new();
+    for item in files.iter().flat_map(|file| &file.items) {
+        match item {
+            Item::Fn(func) if has_attr(&func.attrs, "export_iterator") || has_attr(&func.attrs, "no_mangle") => fns.push(Cow::Borrowed(func)),
+            Item::Macro(item) if item.mac.path.is_ident("ffi_vec_free") => {
+                for FreeEntry { name, ty } in item.mac.parse_body_with(Punctuated::<FreeEntry, Token![,]>::parse_terminated)? {
+                    let doc = format!(" Frees an `FfiVec<{}>` that was handed to `C#`.", ty.to_token_stream());
+                    fns.push(Cow::Owned(parse_quote! {
+                        #[doc = #doc]
+                        #[no_mangle]
+                        pub extern "C" fn #name(v: FfiVec<#ty>) {}
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(fns)
+}
+
+/// One `name: type` in `ffi_vec_free!`
+struct FreeEntry {
+    name: Ident,
+    ty: Type,
+}
+
+impl Parse for FreeEntry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(FreeEntry { name, ty: input.parse()? })
+    }
+}
+
+fn is_repr_c(attr: &Attribute) -> bool {
+    if !attr.path().is_ident("repr") {
+        return false;
+    }
+    let mut c = false;
+    let _ = attr.parse_nested_meta(|meta| {
+        c |= meta.path.is_ident("C");
+        Ok(())
+    });
+    c
+}
+
+/// Reads a crate's `lib.rs` along with the modules it declares with
+/// `mod name;`, returning the source of each, starting with `lib.rs`.
+/// Modules behind `#[cfg(test)]` never make it into the library, so
+/// they're skipped
+pub fn crate_sources(lib_rs: &Path) -> io::Result<Vec<String>> {
+    let lib = std::fs::read_to_string(lib_rs)?;
+    let file = syn::parse_file(&lib).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let dir = lib_rs.parent().unwrap_or_else(|| Path::new("."));
+    let mut sources = vec![lib.clone()];
+    for item in &file.items {
+        if let Item::Mod(module) = item {
+            // Modules written out inline are already part of `lib.rs`
+            if module.content.is_some() || module.attrs.iter().any(is_cfg_test) {
+                continue;
+            }
+            let name = module.ident.to_string();
+            let flat = dir.join(format!("{}.rs", name));
+            let path = if flat.exists() { flat } else { dir.join(&name).join("mod.rs") };
+            sources.push(std::fs::read_to_string(path)?);
+        }
+    }
+    Ok(sources)
+}
+
+fn is_cfg_test(attr: &Attribute) -> bool {
+    attr.path().is_ident("cfg") && attr.parse_args::<Ident>().is_ok_and(|ident| ident == "test")
+}
+
+/// Collects lines at the right indentation
+#[derive(Default)]
+struct Writer {
+    text: String,
+    indent: usize,
+}
+
+impl Writer {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str("    ");
+            }
+            self.text.push_str(line);
+        }
+        self.text.push('\n');
+    }
+
+    fn open(&mut self) {
+        self.line("{");
+        self.indent += 1;
+    }
+
+    fn close(&mut self) {
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// Writes `docs` as an XML doc comment, if there are any
+    fn summary(&mut self, docs: &[String]) {
+        if docs.is_empty() {
+            return;
+        }
+        self.line("/// <summary>");
+        for doc in docs {
+            self.line(format!("/// {}", escape(doc)).trim_end());
+        }
+        self.line("/// </summary>");
+    }
+}
+
+fn find_enum<'a>(items: &[&'a Item], name: &str) -> syn::Result<&'a ItemEnum> {
+    items
+        .iter()
+        .find_map(|item| match item {
+            Item::Enum(item) if item.ident == name => Some(item),
+            _ => None,
+        })
+        .ok_or_else(|| missing(name))
+}
+
+fn find_struct<'a>(items: &[&'a Item], name: &str) -> syn::Result<&'a ItemStruct> {
+    items
+        .iter()
+        .find_map(|item| match item {
+            Item::Struct(item) if item.ident == name => Some(item),
+            _ => None,
+        })
+        .ok_or_else(|| missing(name))
+}
+
+fn missing(name: &str) -> syn::Error {
+    syn::Error::new(Span::call_site(), format!("`{}` isn't defined in the source", name))
+}
+
+/// The doc comment on an item, one line per line, up to a `# Safety`
+/// section, since that's about calling it from rust
+fn docs(attrs: &[Attribute]) -> Vec<String> {
+    let mut lines = doc_lines(attrs);
+    if let Some(safety) = lines.iter().position(|line| line.trim() == "# Safety") {
+        lines.truncate(safety);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// The whole doc comment on an item, one line per line
+fn doc_lines(attrs: &[Attribute]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        let value = match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => &meta.value,
+            _ => continue,
+        };
+        if let Expr::Lit(expr) = value {
+            if let Lit::Str(doc) = &expr.lit {
+                let doc = doc.value();
+                lines.push(doc.strip_prefix(' ').unwrap_or(&doc).to_string());
+            }
+        }
+    }
+    lines
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident(name))
+}
+
+/// `snake_case` to `PascalCase`
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// `snake_case` to `camelCase`, escaped if it's a keyword
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    let camel: String = match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    };
+    // Every reserved keyword in C#, contextual ones like `var` can be
+    // used as names as they are
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char", "checked", "class", "const", "continue", "decimal",
+        "default", "delegate", "do", "double", "else", "enum", "event", "explicit", "extern", "false", "finally", "fixed", "float", "for",
+        "foreach", "goto", "if", "implicit", "in", "int", "interface", "internal", "is", "lock", "long", "namespace", "new", "null",
+        "object", "operator", "out", "override", "params", "private", "protected", "public", "readonly", "ref", "return", "sbyte",
+        "sealed", "short", "sizeof", "stackalloc", "static", "string", "struct", "switch", "this", "throw", "true", "try", "typeof",
+        "uint", "ulong", "unchecked", "unsafe", "ushort", "using", "virtual", "void", "volatile", "while",
+    ];
+    if KEYWORDS.contains(&camel.as_str()) {
+        format!("@{}", camel)
+    } else {
+        camel
+    }
+}
+
+fn renamed(table: &[(&str, &'static str)], name: &str) -> Option<&'static str> {
+    table.iter().find(|(from, _)| *from == name).map(|(_, to)| *to)
+}
+
+fn cs_struct_name(name: &str) -> String {
+    renamed(STRUCT_NAMES, name).unwrap_or(name).to_string()
+}
+
+fn field_name(item: &str, name: &str) -> String {
+    match FIELD_NAMES.iter().find(|(ty, field, _)| *ty == item && *field == name) {
+        Some((_, _, renamed)) => renamed.to_string(),
+        None => pascal_case(name),
+    }
+}
+
+fn delegate_name(name: &str) -> String {
+    renamed(DELEGATE_NAMES, name).map_or_else(|| format!("RustIterator{}", pascal_case(name)), str::to_string)
+}
+
+/// The last identifier of a plain path type, like `Vec` in
+/// `std::vec::Vec<T>`
+fn last_ident(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(ty) if ty.qself.is_none() => ty.path.segments.last(),
+        _ => None,
+    }
+}
+
+/// The `C#` type of a primitive, if `name` is one
+fn primitive(name: &str) -> Option<&'static str> {
+    let cs = match name {
+        "u8" => "byte",
+        "u16" => "ushort",
+        "u32" => "uint",
+        "u64" => "ulong",
+        "usize" => "UIntPtr",
+        "i8" => "sbyte",
+        "i16" => "short",
+        "i32" => "int",
+        "i64" => "long",
+        "isize" => "IntPtr",
+        "f32" => "float",
+        "f64" => "double",
+        "bool" => "bool",
+        "IterStatus" => "RustIterStatus",
+        _ => return None,
+    };
+    Some(cs)
+}
+
+/// `bool`s are a single byte in rust, but four by default in `C#`
+fn marshal_attr(ty: &Type) -> &'static str {
+    match last_ident(ty) {
+        Some(segment) if segment.ident == "bool" => "[MarshalAs(UnmanagedType.U1)] ",
+        _ => "",
+    }
+}
+
+/// The crate's structs and type aliases, along with the structs the
+/// bindings need so far
+struct Types<'a> {
+    structs: HashMap<String, &'a ItemStruct>,
+    aliases: HashMap<String, &'a Type>,
+    /// The structs to generate and their `C#` names, in the order they
+    /// were first needed
+    needed: Vec<(String, &'a ItemStruct)>,
+}
+
+impl<'a> Types<'a> {
+    fn new(items: &[&'a Item]) -> Self {
+        let mut types = Types {
+            structs: HashMap::new(),
+            aliases: HashMap::new(),
+            needed: Vec::new(),
+        };
+        for item in items {
+            match item {
+                Item::Struct(item) => {
+                    types.structs.insert(item.ident.to_string(), item);
+                }
+                Item::Type(item) => {
+                    types.aliases.insert(item.ident.to_string(), &item.ty);
+                }
+                _ => {}
+            }
+        }
+        types
+    }
+
+    fn need(&mut self, name: String, item: &'a ItemStruct) {
+        if !self.needed.iter().any(|(needed, _)| *needed == name) {
+            self.needed.push((name, item));
+        }
+    }
+
+    /// The `#[repr(C)]` struct `name` stands for, directly or through an
+    /// alias like `FfiUtf16`
+    fn repr_c_struct(&self, name: &str) -> Option<&'a ItemStruct> {
+        let item = match self.aliases.get(name) {
+            Some(alias) => self.structs.get(&last_ident(alias)?.ident.to_string())?,
+            None => self.structs.get(name)?,
+        };
+        Some(*item).filter(|item| item.attrs.iter().any(is_repr_c))
+    }
+
+    /// The `C#` type of a value of type `ty`, as a field, argument or
+    /// return value. Structs get one `C#` struct no matter what their
+    /// generic arguments are, since those are only ever behind pointers
+    fn value_type(&mut self, ty: &Type) -> syn::Result<String> {
+        let segment = match ty {
+            Type::Ptr(_) | Type::BareFn(_) => return Ok("IntPtr".to_string()),
+            ty => last_ident(ty),
+        };
+        let segment = match segment {
+            Some(segment) => segment,
+            None => return Err(syn::Error::new(ty.span(), "there's no C# type for this")),
+        };
+        // A nullable function pointer
+        if segment.ident == "Option" && matches!(generic_arg(segment), Some(Type::BareFn(_))) {
+            return Ok("IntPtr".to_string());
+        }
+        let name = segment.ident.to_string();
+        if let Some(cs) = primitive(&name) {
+            return Ok(cs.to_string());
+        }
+        match self.repr_c_struct(&name) {
+            Some(item) => {
+                let cs = cs_struct_name(&name);
+                self.need(cs.clone(), item);
+                Ok(cs)
+            }
+            None => Err(syn::Error::new(ty.span(), format!("`{}` isn't a `#[repr(C)]` struct in the source", name))),
+        }
+    }
+
+    /// The `C#` type of an argument or return value of an imported
+    /// function, where pointers to things `C#` knows about stay pointers
+    fn param_type(&mut self, ty: &Type) -> syn::Result<String> {
+        let elem = match ty {
+            Type::Ptr(ptr) => &ptr.elem,
+            ty => return self.value_type(ty),
+        };
+        let name = match last_ident(elem) {
+            Some(segment) => segment.ident.to_string(),
+            None => return Ok("IntPtr".to_string()),
+        };
+        if let Some(cs) = primitive(&name) {
+            return Ok(format!("{}*", cs));
+        }
+        match self.repr_c_struct(&name) {
+            Some(_) => Ok(format!("{}*", self.value_type(elem)?)),
+            // Anything else, like `CancellationToken`, is opaque
+            None => Ok("IntPtr".to_string()),
+        }
+    }
+
+    fn write_struct(&mut self, out: &mut Writer, item: &ItemStruct, name: &str) -> syn::Result<()> {
+        let fields = match &item.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new(item.span(), "expected named fields")),
+        };
+        out.summary(&docs(&item.attrs));
+        out.line("[StructLayout(LayoutKind.Sequential)]");
+        out.line(&format!("public partial struct {}", name));
+        out.open();
+        for field in fields {
+            let ident = field.ident.as_ref().expect("named fields have names").to_string();
+            // A generic field by value would change shape with its argument
+            if item.generics.type_params().any(|param| last_ident(&field.ty).is_some_and(|segment| segment.ident == param.ident)) {
+                return Err(syn::Error::new(field.ty.span(), "generic fields can't be generated for `C#`"));
+            }
+            out.summary(&docs(&field.attrs));
+            if item.ident == "CSharpIteratorOut" {
+                if let Type::BareFn(_) = &field.ty {
+                    out.line("/// <remarks>");
+                    out.line(&format!("/// Equivalent to a {}", delegate_name(&ident)));
+                    out.line("/// </remarks>");
+                }
+            }
+            let attr = marshal_attr(&field.ty);
+            if !attr.is_empty() {
+                out.line(attr.trim_end());
+            }
+            out.line(&format!("public {} {};", self.value_type(&field.ty)?, field_name(&item.ident.to_string(), &ident)));
+        }
+        out.close();
+        Ok(())
+    }
+
+    /// The `C#` type of a delegate argument, where pointers to the item
+    /// become `void*` and a `*mut IterStatus` becomes an `out` parameter
+    fn delegate_type(&mut self, ty: &Type, generic: &syn::Ident) -> syn::Result<String> {
+        let ptr = match ty {
+            Type::Ptr(ptr) => ptr,
+            ty => return self.value_type(ty),
+        };
+        match last_ident(&ptr.elem) {
+            Some(segment) if segment.ident == "c_void" => Ok("IntPtr".to_string()),
+            Some(segment) if segment.ident == *generic => Ok("void*".to_string()),
+            Some(segment) if segment.ident == "IterStatus" && ptr.mutability.is_some() => Ok("out RustIterStatus".to_string()),
+            _ => Ok(format!("{}*", self.value_type(&ptr.elem)?)),
+        }
+    }
+
+    fn write_delegates(&mut self, out: &mut Writer, item: &ItemStruct) -> syn::Result<()> {
+        let generic = match item.generics.type_params().next() {
+            Some(param) => &param.ident,
+            None => return Err(syn::Error::new(item.span(), "expected the item type parameter")),
+        };
+        for field in &item.fields {
+            let func = match &field.ty {
+                Type::BareFn(func) => func,
+                _ => continue,
+            };
+            let ident = field.ident.as_ref().expect("named fields have names").to_string();
+            let mut params = Vec::new();
+            for (i, arg) in func.inputs.iter().enumerate() {
+                let name = match &arg.name {
+                    Some((name, _)) => camel_case(&name.to_string()),
+                    None => format!("arg{}", i),
+                };
+                params.push(format!("{} {}", self.delegate_type(&arg.ty, generic)?, name));
+            }
+            let ret = match &func.output {
+                ReturnType::Default => "void".to_string(),
+                ReturnType::Type(_, ty) => self.delegate_type(ty, generic)?,
+            };
+            let unsafety = if params.iter().any(|param| param.contains('*')) { "unsafe " } else { "" };
+            out.summary(&docs(&field.attrs));
+            out.line(&format!("public {}delegate {} {}({});", unsafety, ret, delegate_name(&ident), params.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+fn write_enum(out: &mut Writer, item: &ItemEnum) -> syn::Result<()> {
+    out.summary(&docs(&item.attrs));
+    out.line("public enum RustIterStatus : int");
+    out.open();
+    for variant in &item.variants {
+        let value = match &variant.discriminant {
+            Some((_, Expr::Lit(expr))) => match &expr.lit {
+                Lit::Int(value) => value.base10_digits().to_string(),
+                lit => return Err(syn::Error::new(lit.span(), "expected an integer")),
+            },
+            _ => return Err(syn::Error::new(variant.span(), "every status needs an explicit value")),
+        };
+        out.summary(&docs(&variant.attrs));
+        out.line(&format!("{} = {},", variant.ident, value));
+    }
+    out.close();
+    Ok(())
+}
+
+/// How an argument of an exported function crosses over, the same as in
+/// `#[export_iterator]`
+enum Param {
+    /// As it is
+    Plain(String, Type),
+    /// As a pointer to UTF-8 and a length
+    String(String),
+    /// As a pointer to the first element and a length
+    Vec(String, Type),
+}
+
+/// A function rust exports
+struct Export {
+    name: String,
+    docs: Vec<String>,
+    kind: ExportKind,
+}
+
+enum ExportKind {
+    /// One that writes an iterator (or a stream) to an out pointer, which
+    /// gets a factory method
+    Iterator {
+        item: Box<Type>,
+        params: Vec<Param>,
+        /// Whether it writes a `CSharpStreamOut`, which `C#` wraps in a
+        /// `RustStream<T>` instead of a `RustIter<T>`
+        stream: bool,
+        /// Whether it returns whether it worked, which `#[export_iterator]`
+        /// functions do. Others only say so through the last error
+        checked: bool,
+    },
+    /// Anything else, which is imported as it is
+    Plain { params: Vec<(String, Type)>, output: ReturnType },
+}
+
+impl Export {
+    fn from_fn(func: &ItemFn) -> syn::Result<Self> {
+        if has_attr(&func.attrs, "export_iterator") {
+            return Self::from_macro(func);
+        }
+        let mut params = func.sig.inputs.iter().map(|input| typed_arg(input).map(|(name, ty)| (name, ty.clone()))).collect::<syn::Result<Vec<_>>>()?;
+        let item = params.first().and_then(|(_, ty)| match ty {
+            Type::Ptr(ptr) if ptr.mutability.is_some() => match last_ident(&ptr.elem) {
+                Some(segment) if segment.ident == "CSharpIteratorOut" => generic_arg(segment).cloned().map(|item| (item, false)),
+                Some(segment) if segment.ident == "CSharpStreamOut" => generic_arg(segment).cloned().map(|item| (item, true)),
+                _ => None,
+            },
+            _ => None,
+        });
+        let kind = match item {
+            Some((item, stream)) => ExportKind::Iterator {
+                item: Box::new(item),
+                params: params.drain(1..).map(|(name, ty)| Param::Plain(name, ty)).collect(),
+                stream,
+                checked: false,
+            },
+            None => ExportKind::Plain { params, output: func.sig.output.clone() },
+        };
+        Ok(Export { name: func.sig.ident.to_string(), docs: docs(&func.attrs), kind })
+    }
+
+    /// An `#[export_iterator]` function, as it was before the macro got
+    /// to it
+    fn from_macro(func: &ItemFn) -> syn::Result<Self> {
+        let mut params = Vec::new();
+        for input in &func.sig.inputs {
+            let (name, ty) = typed_arg(input)?;
+            let param = match last_ident(ty) {
+                Some(segment) if segment.ident == "String" => Param::String(name),
+                Some(segment) if segment.ident == "Vec" => Param::Vec(name, generic_arg(segment).cloned().ok_or_else(|| syn::Error::new(ty.span(), "expected `Vec<T>`"))?),
+                _ => Param::Plain(name, ty.clone()),
+            };
+            params.push(param);
+        }
+        Ok(Export {
+            name: func.sig.ident.to_string(),
+            docs: docs(&func.attrs),
+            kind: ExportKind::Iterator { item: Box::new(iterator_item(&func.sig.output)?), params, stream: false, checked: true },
+        })
+    }
+}
+
+fn typed_arg(input: &FnArg) -> syn::Result<(String, &Type)> {
+    match input {
+        FnArg::Typed(arg) => match &*arg.pat {
+            Pat::Ident(pat) => Ok((pat.ident.to_string(), &arg.ty)),
+            pat => Err(syn::Error::new(pat.span(), "expected a plain name")),
+        },
+        FnArg::Receiver(receiver) => Err(syn::Error::new(receiver.span(), "exports can't take `self`")),
+    }
+}
+
+/// The `T` in `Name<T>`
+fn generic_arg(segment: &syn::PathSegment) -> Option<&Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// The `T` in `-> impl Iterator<Item=T>`
+fn iterator_item(output: &ReturnType) -> syn::Result<Type> {
+    if let ReturnType::Type(_, ty) = output {
+        if let Type::ImplTrait(ty) = &**ty {
+            for bound in &ty.bounds {
+                if let TypeParamBound::Trait(bound) = bound {
+                    if let Some(PathArguments::AngleBracketed(args)) = bound.path.segments.last().map(|last| &last.arguments) {
+                        for arg in &args.args {
+                            if let GenericArgument::AssocType(assoc) = arg {
+                                if assoc.ident == "Item" {
+                                    return Ok(assoc.ty.clone());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Err(syn::Error::new(output.span(), "expected `impl Iterator<Item=T>`"))
+}
+
+fn write_exports(out: &mut Writer, types: &mut Types, exports: &[Export], options: &Options) -> syn::Result<()> {
+    out.line("/// <summary>");
+    out.line("/// Every function rust exports, with a factory method returning a");
+    out.line("/// `RustIter<T>` or a `RustStream<T>` for each one that makes an");
+    out.line("/// iterator or a stream");
+    out.line("/// </summary>");
+    out.line("public static class RustExports");
+    out.open();
+    for (i, export) in exports.iter().enumerate() {
+        if i != 0 {
+            out.line("");
+        }
+        match &export.kind {
+            ExportKind::Iterator { item, params, stream, checked } => write_iterator(out, types, export, item, params, (*stream, *checked), options)?,
+            ExportKind::Plain { params, output } => {
+                let mut imported = Vec::new();
+                for (name, ty) in params {
+                    imported.push(format!("{}{} {}", marshal_attr(ty), types.param_type(ty)?, camel_case(name)));
+                }
+                let ret = match output {
+                    ReturnType::Default => "void".to_string(),
+                    ReturnType::Type(_, ty) => types.param_type(ty)?,
+                };
+                let unsafety = if ret.contains('*') || imported.iter().any(|param| param.contains('*')) { "unsafe " } else { "" };
+                out.summary(&export.docs);
+                out.line(&format!("[DllImport(\"{}\")]", options.library));
+                if ret == "bool" {
+                    out.line("[return: MarshalAs(UnmanagedType.U1)]");
+                }
+                out.line(&format!("public static extern {}{} {}({});", unsafety, ret, export.name, imported.join(", ")));
+            }
+        }
+    }
+    out.close();
+    Ok(())
+}
+
+fn write_iterator(out: &mut Writer, types: &mut Types, export: &Export, item: &Type, params: &[Param], (stream, checked): (bool, bool), options: &Options) -> syn::Result<()> {
+    let item = types.value_type(item)?;
+    let (ffi, wrapper) = if stream { ("RustFFIStream", "RustStream") } else { ("RustFFIIterator", "RustIter") };
+    // What the `DllImport` takes, what the factory takes, and what the
+    // factory passes on
+    let mut imported = vec![format!("out {} iter", ffi)];
+    let mut taken = Vec::new();
+    let mut passed = vec!["out var iter".to_string()];
+    let mut prepare = Vec::new();
+    for param in params {
+        match param {
+            Param::Plain(name, ty) => {
+                let (name, cs) = (camel_case(name), types.value_type(ty)?);
+                imported.push(format!("{}{} {}", marshal_attr(ty), cs, name));
+                taken.push(format!("{} {}", cs, name));
+                passed.push(name);
+            }
+            Param::String(name) => {
+                let name = camel_case(name);
+                let bytes = format!("{}Bytes", name.trim_start_matches('@'));
+                imported.push(format!("byte[] {}, UIntPtr {}Len", name, name.trim_start_matches('@')));
+                taken.push(format!("string {}", name));
+                prepare.push(format!("var {} = Encoding.UTF8.GetBytes({});", bytes, name));
+                passed.push(format!("{}, (UIntPtr){}.Length", bytes, bytes));
+            }
+            Param::Vec(name, elem) => {
+                let (name, cs) = (camel_case(name), types.value_type(elem)?);
+                imported.push(format!("{}[] {}, UIntPtr {}Len", cs, name, name.trim_start_matches('@')));
+                taken.push(format!("{}[] {}", cs, name));
+                passed.push(format!("{}, (UIntPtr){}.Length", name, name));
+            }
+        }
+    }
+    out.line(&format!("[DllImport(\"{}\")]", options.library));
+    if checked {
+        out.line("[return: MarshalAs(UnmanagedType.U1)]");
+    }
+    let ret = if checked { "bool" } else { "void" };
+    out.line(&format!("private static extern {} {}({});", ret, export.name, imported.join(", ")));
+    out.summary(&export.docs);
+    out.line(&format!("public static {}<{}> {}({})", wrapper, item, pascal_case(&export.name), taken.join(", ")));
+    out.open();
+    for line in &prepare {
+        out.line(line);
+    }
+    let call = format!("{}({})", export.name, passed.join(", "));
+    if checked {
+        out.line(&format!("if (!{})", call));
+    } else {
+        out.line(&format!("{};", call));
+        out.line("if (RustLastError.Code != RustIterStatus.Item)");
+    }
+    out.open();
+    out.line("throw RustLastError.ToException();");
+    out.close();
+    out.line(&format!("return new {}<{}>(iter);", wrapper, item));
+    out.close();
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! `cs_iter_bindgen [--header] <lib.rs> [out]`
+//!
+//! Writes the `C#` bindings for the crate whose `lib.rs` is given to
+//! `out`, or to stdout, covering the modules `lib.rs` declares as well.
+//! With `--header` it writes a C header instead.
+
+use std::path::Path;
+use std::process::exit;
+
+use cs_iter_bindgen::{crate_sources, generate, generate_header, Options};
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let header = args.first().is_some_and(|arg| arg == "--header");
+    if header {
+        args.remove(0);
+    }
+    let (source, out) = match args.as_slice() {
+        [source] => (source, None),
+        [source, out] => (source, Some(out)),
+        _ => {
+            eprintln!("usage: cs_iter_bindgen [--header] <lib.rs> [out]");
+            exit(2);
+        }
+    };
+    let sources = match crate_sources(Path::new(source)) {
+        Ok(sources) => sources,
+        Err(e) => {
+            eprintln!("couldn't read {}: {}", source, e);
+            exit(1);
+        }
+    };
+    let result = if header { generate_header(&sources) } else { generate(&sources, &Options::default()) };
+    let text = match result {
+        Ok(text) => text,
+        Err(e) => {
+            let start = e.span().start();
+            // The span doesn't say which of the modules it's in
+            eprintln!("{} or one of its modules, {}:{}: {}", source, start.line, start.column + 1, e);
+            exit(1);
+        }
+    };
+    match out {
+        Some(out) => {
+            if let Err(e) = std::fs::write(out, text) {
+                eprintln!("couldn't write {}: {}", out, e);
+                exit(1);
+            }
+        }
+        None => print!("{}", text),
+    }
+}
@@ -0,0 +1,52 @@
+/// What `internal_iter` says
+#[repr(C)]
+pub enum IterStatus {
+    /// An item & nothing else
+    Item = 0,
+    Exhausted = 1,
+}
+
+#[repr(C)]
+pub struct FfiSizeHint {
+    pub lower: usize,
+    pub has_upper: bool,
+}
+
+/// The iterator
+#[repr(C)]
+pub struct CSharpIteratorOut<T> {
+    /// Gets the next `T`
+    pub internal_iter: unsafe extern "C" fn(iter: *mut c_void, data: *mut T) -> IterStatus,
+    pub pointer: *mut c_void,
+    pub next_chunk: unsafe extern "C" fn(*mut c_void, *mut T, usize, *mut IterStatus) -> usize,
+}
+
+/// Written by hand
+///
+/// # Safety
+/// Not part of the bindings.
+#[no_mangle]
+pub unsafe extern "C" fn get_numbers(cs: *mut CSharpIteratorOut<i32>, count: u32, flag: bool, class: u8, is_new: bool) {}
+
+/// Written by hand as well, but for a stream
+///
+/// # Safety
+/// Not part of the bindings.
+#[no_mangle]
+pub unsafe extern "C" fn get_ticks(cs: *mut CSharpStreamOut<u64>, count: u64) {}
+
+/// Not an iterator, so it's imported as it is
+#[no_mangle]
+pub extern "C" fn sum(a: u64) -> u64 {
+    a
+}
+
+#[export_iterator]
+pub fn get_words(text: String, lengths: Vec<u16>, params: u8, long: Vec<u8>, this: String) -> impl ExactSizeIterator<Item=FfiUtf16> + Send {
+    std::iter::empty()
+}
+
+#[export_iterator]
+fn get_vecs() -> impl Iterator<Item=FfiVec<f64>> {
+    std::iter::empty()
+}
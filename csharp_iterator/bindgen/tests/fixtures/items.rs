@@ -0,0 +1,36 @@
+/// Owned elements
+#[repr(C)]
+pub struct FfiVec<T> {
+    ptr: *mut T,
+    len: usize,
+    capacity: usize,
+}
+
+#[repr(C)]
+pub struct FfiString<C> {
+    /// The first unit
+    ptr: *const C,
+    len: usize,
+}
+
+pub type FfiUtf16 = FfiString<u16>;
+
+/// Not `#[repr(C)]`, so it never crosses over
+pub struct CancellationToken(Arc<AtomicBool>);
+
+/// Hands out a token
+#[no_mangle]
+pub extern "C" fn token_new() -> *mut CancellationToken {
+    Box::into_raw(Box::default())
+}
+
+/// # Safety
+/// `buf` must be valid for `len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn fill(buf: *mut u16, len: usize, hint: *const FfiSizeHint) -> bool {
+    true
+}
+
+ffi_vec_free! {
+    ffi_vec_f64_free: f64,
+}
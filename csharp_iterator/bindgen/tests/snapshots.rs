@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+
+use cs_iter_bindgen::{crate_sources, generate, generate_header, Options};
+
+/// Checks `actual` against the file at `path`, or overwrites the file
+/// when `UPDATE_SNAPSHOTS` is set
+fn assert_snapshot(path: &Path, actual: &str) {
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap_or_else(|e| panic!("couldn't read {}: {}", path.display(), e));
+    assert!(
+        expected == actual,
+        "{} is out of date, run with UPDATE_SNAPSHOTS=1 to regenerate it.\n\n{}",
+        path.display(),
+        actual
+    );
+}
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The fixture, split over two files like a crate with a module
+fn fixture_sources() -> Vec<String> {
+    ["exports.rs", "items.rs"].iter().map(|file| fs::read_to_string(manifest_dir().join("tests/fixtures").join(file)).unwrap()).collect()
+}
+
+#[test]
+fn fixture() {
+    let sources = fixture_sources();
+    let options = Options {
+        namespace: "Fixture".to_string(),
+        library: "fixture.dll".to_string(),
+    };
+    let bindings = generate(&sources, &options).unwrap();
+    assert_snapshot(&manifest_dir().join("tests/snapshots/exports.cs"), &bindings);
+}
+
+/// The bindings the `C#` project uses have to match the crate as it is
+#[test]
+fn crate_bindings() {
+    let sources = crate_sources(&manifest_dir().join("../src/lib.rs")).unwrap();
+    let bindings = generate(&sources, &Options::default()).unwrap();
+    assert_snapshot(&manifest_dir().join("../../RustIterator/Bindings.cs"), &bindings);
+}
+
+/// So does the header for everyone else
+#[test]
+fn crate_header() {
+    let sources = crate_sources(&manifest_dir().join("../src/lib.rs")).unwrap();
+    let header = generate_header(&sources).unwrap();
+    assert_snapshot(&manifest_dir().join("../include/cs_iter.h"), &header);
+}
+
+#[test]
+fn missing_types() {
+    let error = generate(&["pub struct FfiSizeHint;".to_string()], &Options::default()).unwrap_err();
+    assert_eq!(error.to_string(), "`IterStatus` isn't defined in the source");
+}
+
+#[test]
+fn non_iterator_export() {
+    let mut sources = fixture_sources();
+    sources.push("#[export_iterator]\nfn bad() -> Vec<u8> { vec![] }\n".to_string());
+    let error = generate(&sources, &Options::default()).unwrap_err();
+    assert_eq!(error.to_string(), "expected `impl Iterator<Item=T>`");
+}
+
+/// Every struct an export uses has to be one `C#` can declare
+#[test]
+fn non_repr_c_item() {
+    let mut sources = fixture_sources();
+    sources.push("pub struct Loose(u8);\n#[export_iterator]\nfn loose() -> impl Iterator<Item=Loose> { std::iter::empty() }\n".to_string());
+    let error = generate(&sources, &Options::default()).unwrap_err();
+    assert_eq!(error.to_string(), "`Loose` isn't a `#[repr(C)]` struct in the source");
+}
@@ -0,0 +1,157 @@
+// <auto-generated>
+// Generated by cs_iter_bindgen from the rust source. Don't edit this
+// by hand, run cs_iter_bindgen again instead.
+// </auto-generated>
+using System;
+using System.Runtime.InteropServices;
+using System.Text;
+
+namespace Fixture
+{
+    /// <summary>
+    /// What `internal_iter` says
+    /// </summary>
+    public enum RustIterStatus : int
+    {
+        /// <summary>
+        /// An item &amp; nothing else
+        /// </summary>
+        Item = 0,
+        Exhausted = 1,
+    }
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustSizeHint
+    {
+        public UIntPtr Lower;
+        [MarshalAs(UnmanagedType.U1)]
+        public bool HasUpper;
+    }
+    /// <summary>
+    /// The iterator
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct RustFFIIterator
+    {
+        /// <summary>
+        /// Gets the next `T`
+        /// </summary>
+        /// <remarks>
+        /// Equivalent to a RustIteratorNext
+        /// </remarks>
+        public IntPtr Next;
+        public IntPtr Iterator;
+        /// <remarks>
+        /// Equivalent to a RustIteratorNextChunk
+        /// </remarks>
+        public IntPtr NextChunk;
+    }
+    /// <summary>
+    /// Gets the next `T`
+    /// </summary>
+    public unsafe delegate RustIterStatus RustIteratorNext(IntPtr iter, void* data);
+    public unsafe delegate UIntPtr RustIteratorNextChunk(IntPtr arg0, void* arg1, UIntPtr arg2, out RustIterStatus arg3);
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct FfiUtf16
+    {
+        /// <summary>
+        /// The first unit
+        /// </summary>
+        public IntPtr Ptr;
+        public UIntPtr Len;
+    }
+    /// <summary>
+    /// Owned elements
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public partial struct FfiVec
+    {
+        public IntPtr Ptr;
+        public UIntPtr Len;
+        public UIntPtr Capacity;
+    }
+    /// <summary>
+    /// Every function rust exports, with a factory method returning a
+    /// `RustIter<T>` or a `RustStream<T>` for each one that makes an
+    /// iterator or a stream
+    /// </summary>
+    public static class RustExports
+    {
+        [DllImport("fixture.dll")]
+        private static extern void get_numbers(out RustFFIIterator iter, uint count, [MarshalAs(UnmanagedType.U1)] bool flag, byte @class, [MarshalAs(UnmanagedType.U1)] bool isNew);
+        /// <summary>
+        /// Written by hand
+        /// </summary>
+        public static RustIter<int> GetNumbers(uint count, bool flag, byte @class, bool isNew)
+        {
+            get_numbers(out var iter, count, flag, @class, isNew);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<int>(iter);
+        }
+
+        [DllImport("fixture.dll")]
+        private static extern void get_ticks(out RustFFIStream iter, ulong count);
+        /// <summary>
+        /// Written by hand as well, but for a stream
+        /// </summary>
+        public static RustStream<ulong> GetTicks(ulong count)
+        {
+            get_ticks(out var iter, count);
+            if (RustLastError.Code != RustIterStatus.Item)
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustStream<ulong>(iter);
+        }
+
+        /// <summary>
+        /// Not an iterator, so it's imported as it is
+        /// </summary>
+        [DllImport("fixture.dll")]
+        public static extern ulong sum(ulong a);
+
+        [DllImport("fixture.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        private static extern bool get_words(out RustFFIIterator iter, byte[] text, UIntPtr textLen, ushort[] lengths, UIntPtr lengthsLen, byte @params, byte[] @long, UIntPtr longLen, byte[] @this, UIntPtr thisLen);
+        public static RustIter<FfiUtf16> GetWords(string text, ushort[] lengths, byte @params, byte[] @long, string @this)
+        {
+            var textBytes = Encoding.UTF8.GetBytes(text);
+            var thisBytes = Encoding.UTF8.GetBytes(@this);
+            if (!get_words(out var iter, textBytes, (UIntPtr)textBytes.Length, lengths, (UIntPtr)lengths.Length, @params, @long, (UIntPtr)@long.Length, thisBytes, (UIntPtr)thisBytes.Length))
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<FfiUtf16>(iter);
+        }
+
+        [DllImport("fixture.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        private static extern bool get_vecs(out RustFFIIterator iter);
+        public static RustIter<FfiVec> GetVecs()
+        {
+            if (!get_vecs(out var iter))
+            {
+                throw RustLastError.ToException();
+            }
+            return new RustIter<FfiVec>(iter);
+        }
+
+        /// <summary>
+        /// Hands out a token
+        /// </summary>
+        [DllImport("fixture.dll")]
+        public static extern IntPtr token_new();
+
+        [DllImport("fixture.dll")]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern unsafe bool fill(ushort* buf, UIntPtr len, RustSizeHint* hint);
+
+        /// <summary>
+        /// Frees an `FfiVec&lt;f64&gt;` that was handed to `C#`.
+        /// </summary>
+        [DllImport("fixture.dll")]
+        public static extern void ffi_vec_f64_free(FfiVec v);
+    }
+}
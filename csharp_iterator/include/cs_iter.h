@@ -0,0 +1,881 @@
+/* Generated by cs_iter_bindgen from the rust source. Don't edit this
+ * by hand, run cs_iter_bindgen --header again instead. */
+#ifndef CS_ITER_H
+#define CS_ITER_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_usize {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    uintptr_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_usize;
+
+/**
+ * What `internal_iter` tells `C#` after each call.
+ *
+ * `AlreadyFinished`, `Panicked` and `Cancelled` are sticky: once an
+ * iterator returns one of them, every later call returns the same
+ * thing. `Exhausted` is only ever returned once, and is followed by
+ * `AlreadyFinished`. `Item`, `Error` and `InvalidHandle` say nothing
+ * about the next call.
+ */
+typedef enum IterStatus {
+    /**
+     * There was new data, and it was written to the data pointer
+     */
+    ITER_STATUS_ITEM = 0,
+    /**
+     * The iterator just ran out of data, and was dropped
+     */
+    ITER_STATUS_EXHAUSTED = 1,
+    /**
+     * The iterator ran out of data on an earlier call
+     */
+    ITER_STATUS_ALREADY_FINISHED = 2,
+    /**
+     * The iterator panicked, either on this call or on an earlier one.
+     * The iterator isn't touched anymore, and the panic message can be
+     * fetched through `message`
+     */
+    ITER_STATUS_PANICKED = 3,
+    /**
+     * Either the call itself was wrong (like a null data pointer), or
+     * the item was an error (see `form_fallible`), whose message can be
+     * fetched through `message`. Either way, the iterator can still be
+     * polled
+     */
+    ITER_STATUS_ERROR = 4,
+    /**
+     * The iterator pointer doesn't point to an iterator
+     */
+    ITER_STATUS_INVALID_HANDLE = 5,
+    /**
+     * The iterator's `CancellationToken` was triggered, either during
+     * this call or before it. The iterator was dropped, and any item it
+     * produced in the meantime along with it
+     */
+    ITER_STATUS_CANCELLED = 6,
+} IterStatus;
+
+/**
+ * How many items an iterator has left, as told to `C#` by `size_hint`
+ */
+typedef struct FfiSizeHint {
+    /**
+     * The least number of items left
+     */
+    uintptr_t lower;
+    /**
+     * The most number of items left, only meaningful if `has_upper` is set
+     */
+    uintptr_t upper;
+    /**
+     * Whether there is an upper bound at all
+     */
+    bool has_upper;
+    /**
+     * Whether `lower` is exactly the number of items left, which is
+     * only promised for iterators made with `form_exact`, and once an
+     * iterator is done
+     */
+    bool exact;
+} FfiSizeHint;
+
+/**
+ * The "iterator" we pass to `C#`
+ *
+ * `T` can be anything, including types without a sensible `Default`
+ * like `NonZeroU32`: the slot `C#` passes to `internal_iter` is only
+ * ever written to, never read or dropped, so it can hold anything
+ * (including garbage) beforehand.
+ */
+typedef struct CSharpIteratorOut_FfiVec_usize {
+    /**
+     * The function we pass `C#`. It's called by `C#` and recieves the
+     * pointer to the `Box`ed iterator. It's instantiated for the exact
+     * iterator type behind `pointer`, so there's no dynamic dispatch
+     */
+    IterStatus (*internal_iter)(void *iter, FfiVec_usize *data);
+    /**
+     * A thin pointer to the iterator's state that gets leaked. Only the
+     * functions next to it know what type it really points to
+     */
+    void *pointer;
+    /**
+     * The function `C#` calls once it's done with the iterator, whether
+     * or not it was run to the end. Frees `pointer`, after which none
+     * of these functions may be called with it again
+     */
+    void (*destroy)(void *iter);
+    /**
+     * The function `C#` calls to get the panic message after
+     * `internal_iter` returned `IterStatus::Panicked`, or the error
+     * message after it returned `IterStatus::Error` for an error item
+     */
+    uintptr_t (*message)(void *iter, uint8_t *buf, uintptr_t len);
+    /**
+     * The function `C#` calls to hand an item back once it's done with
+     * it. Every item written by `internal_iter` belongs to `C#` until
+     * it's passed here, and this works even after `destroy`
+     */
+    void (*release_item)(FfiVec_usize *item);
+    /**
+     * The function `C#` calls to find out how many items are left
+     */
+    FfiSizeHint (*size_hint)(void *iter);
+    /**
+     * The function `C#` calls to get many items at once, instead of
+     * calling `internal_iter` for each of them
+     */
+    uintptr_t (*next_chunk)(void *iter, FfiVec_usize *buf, uintptr_t len, IterStatus *status);
+} CSharpIteratorOut_FfiVec_usize;
+
+/**
+ * A string handed to `C#` as a pointer and a length in code units,
+ * either UTF-8 bytes (`FfiUtf8`) or UTF-16 chars (`FfiUtf16`, which is
+ * what `C#` strings are made of). There's no nul terminator.
+ *
+ * Each one is its own allocation, owned by whoever holds it: it stays
+ * valid until it's passed to `release_item` or to `ffi_utf8_free` /
+ * `ffi_utf16_free`, even after the iterator it came from is destroyed.
+ */
+typedef struct FfiString_u16 {
+    /**
+     * The pointer to the first code unit, dangling when `len` is 0
+     */
+    uint16_t *ptr;
+    /**
+     * How many code units there are
+     */
+    uintptr_t len;
+} FfiString_u16;
+
+typedef FfiString_u16 FfiUtf16;
+
+/**
+ * The "iterator" we pass to `C#`
+ *
+ * `T` can be anything, including types without a sensible `Default`
+ * like `NonZeroU32`: the slot `C#` passes to `internal_iter` is only
+ * ever written to, never read or dropped, so it can hold anything
+ * (including garbage) beforehand.
+ */
+typedef struct CSharpIteratorOut_FfiUtf16 {
+    /**
+     * The function we pass `C#`. It's called by `C#` and recieves the
+     * pointer to the `Box`ed iterator. It's instantiated for the exact
+     * iterator type behind `pointer`, so there's no dynamic dispatch
+     */
+    IterStatus (*internal_iter)(void *iter, FfiUtf16 *data);
+    /**
+     * A thin pointer to the iterator's state that gets leaked. Only the
+     * functions next to it know what type it really points to
+     */
+    void *pointer;
+    /**
+     * The function `C#` calls once it's done with the iterator, whether
+     * or not it was run to the end. Frees `pointer`, after which none
+     * of these functions may be called with it again
+     */
+    void (*destroy)(void *iter);
+    /**
+     * The function `C#` calls to get the panic message after
+     * `internal_iter` returned `IterStatus::Panicked`, or the error
+     * message after it returned `IterStatus::Error` for an error item
+     */
+    uintptr_t (*message)(void *iter, uint8_t *buf, uintptr_t len);
+    /**
+     * The function `C#` calls to hand an item back once it's done with
+     * it. Every item written by `internal_iter` belongs to `C#` until
+     * it's passed here, and this works even after `destroy`
+     */
+    void (*release_item)(FfiUtf16 *item);
+    /**
+     * The function `C#` calls to find out how many items are left
+     */
+    FfiSizeHint (*size_hint)(void *iter);
+    /**
+     * The function `C#` calls to get many items at once, instead of
+     * calling `internal_iter` for each of them
+     */
+    uintptr_t (*next_chunk)(void *iter, FfiUtf16 *buf, uintptr_t len, IterStatus *status);
+} CSharpIteratorOut_FfiUtf16;
+
+/**
+ * The "iterator" we pass to `C#`
+ *
+ * `T` can be anything, including types without a sensible `Default`
+ * like `NonZeroU32`: the slot `C#` passes to `internal_iter` is only
+ * ever written to, never read or dropped, so it can hold anything
+ * (including garbage) beforehand.
+ */
+typedef struct CSharpIteratorOut_u64 {
+    /**
+     * The function we pass `C#`. It's called by `C#` and recieves the
+     * pointer to the `Box`ed iterator. It's instantiated for the exact
+     * iterator type behind `pointer`, so there's no dynamic dispatch
+     */
+    IterStatus (*internal_iter)(void *iter, uint64_t *data);
+    /**
+     * A thin pointer to the iterator's state that gets leaked. Only the
+     * functions next to it know what type it really points to
+     */
+    void *pointer;
+    /**
+     * The function `C#` calls once it's done with the iterator, whether
+     * or not it was run to the end. Frees `pointer`, after which none
+     * of these functions may be called with it again
+     */
+    void (*destroy)(void *iter);
+    /**
+     * The function `C#` calls to get the panic message after
+     * `internal_iter` returned `IterStatus::Panicked`, or the error
+     * message after it returned `IterStatus::Error` for an error item
+     */
+    uintptr_t (*message)(void *iter, uint8_t *buf, uintptr_t len);
+    /**
+     * The function `C#` calls to hand an item back once it's done with
+     * it. Every item written by `internal_iter` belongs to `C#` until
+     * it's passed here, and this works even after `destroy`
+     */
+    void (*release_item)(uint64_t *item);
+    /**
+     * The function `C#` calls to find out how many items are left
+     */
+    FfiSizeHint (*size_hint)(void *iter);
+    /**
+     * The function `C#` calls to get many items at once, instead of
+     * calling `internal_iter` for each of them
+     */
+    uintptr_t (*next_chunk)(void *iter, uint64_t *buf, uintptr_t len, IterStatus *status);
+} CSharpIteratorOut_u64;
+
+typedef struct CancellationToken CancellationToken;
+
+/**
+ * The callback `C#` passes along with each request for an item, called
+ * exactly once with how the request went. This is what `C#` completes
+ * its `TaskCompletionSource` from.
+ *
+ * It may be called before `request_next` even returns, if the item was
+ * already there, or later from whichever thread woke the stream up.
+ */
+typedef struct CSharpCompletion {
+    /**
+     * Passed back to `complete`, see [Callbacks](crate#callbacks)
+     */
+    void *context;
+    /**
+     * Takes the status of the request: `IterStatus::Item` if the slot
+     * now holds an item, or whatever else stopped the stream
+     */
+    void (*complete)(void *, IterStatus);
+} CSharpCompletion;
+
+/**
+ * The async "iterator" we pass to `C#`, for a `Stream` instead of an
+ * `Iterator`.
+ *
+ * Instead of blocking until the next item is there, `request_next`
+ * polls the stream once and returns, and the request completes through
+ * its `CSharpCompletion` once the stream has something to say. Whoever
+ * wakes the stream up polls it again right there, so there's no
+ * executor or thread of our own behind it.
+ */
+typedef struct CSharpStreamOut_u64 {
+    /**
+     * The function `C#` calls to ask for the next item, with the slot to
+     * write it into and the callback to call once it did. Only one
+     * request can be in flight at a time, another one completes with
+     * `IterStatus::Error` right away
+     */
+    void (*request_next)(void *, uint64_t *, CSharpCompletion);
+    /**
+     * A thin pointer to the stream's state that gets leaked
+     */
+    void *pointer;
+    /**
+     * The function `C#` calls once it's done with the stream. A request
+     * still in flight completes with `IterStatus::AlreadyFinished`
+     */
+    void (*destroy)(void *);
+    /**
+     * The function `C#` calls to get the panic message after a request
+     * completed with `IterStatus::Panicked`
+     */
+    uintptr_t (*message)(void *, uint8_t *, uintptr_t);
+    /**
+     * The function `C#` calls to hand an item back once it's done with it
+     */
+    void (*release_item)(uint64_t *);
+} CSharpStreamOut_u64;
+
+/**
+ * A sequence owned by `C#` (like an `IEnumerable<T>`), handed to rust as
+ * a context pointer and a pair of callbacks. It's a plain `Iterator`, so
+ * rust functions exported by this crate can take one and use all the
+ * usual combinators on it.
+ *
+ * Rust calls `next` until it returns false, and never after that, then
+ * calls `release` exactly once when it drops the iterator, whether or
+ * not it read it to the end.
+ */
+typedef struct ForeignIterator_u64 {
+    /**
+     * Passed back to `next` and `release`, see [Callbacks](crate#callbacks)
+     */
+    void *context;
+    /**
+     * Writes the next item to the slot and returns true, or returns false
+     * once there are no more items. The slot is uninitialized until it's
+     * written to, and rust owns the item afterwards
+     */
+    bool (*next)(void *, uint64_t *);
+    /**
+     * Lets `C#` clean up its side, can be null if there's nothing to do
+     */
+    void (*release)(void *);
+} ForeignIterator_u64;
+
+/**
+ * A callback `C#` gives rust to push items into, for when rust should
+ * drive the loop instead of `C#` calling `internal_iter` over and over.
+ *
+ * Each item is only lent to `push` for the duration of the call, and
+ * dropped by rust afterwards, so `C#` copies out what it needs and never
+ * has to release anything.
+ */
+typedef struct CSharpSink_u64 {
+    /**
+     * Passed back to `push`, see [Callbacks](crate#callbacks)
+     */
+    void *context;
+    /**
+     * Takes the next item, returning false to stop early
+     */
+    bool (*push)(void *, const uint64_t *);
+} CSharpSink_u64;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_u8 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    uint8_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_u8;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_u16 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    uint16_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_u16;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_u32 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    uint32_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_u32;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_u64 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    uint64_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_u64;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_i8 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    int8_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_i8;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_i16 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    int16_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_i16;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_i32 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    int32_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_i32;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_i64 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    int64_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_i64;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_isize {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    intptr_t *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_isize;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_f32 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    float *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_f32;
+
+/**
+ * A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+ * whose field order is up to the compiler. This is what collections
+ * should be sent to `C#` as.
+ */
+typedef struct FfiVec_f64 {
+    /**
+     * The pointer to the first element, dangling when `capacity` is 0
+     */
+    double *ptr;
+    /**
+     * How many elements are initialized
+     */
+    uintptr_t len;
+    /**
+     * How many elements the allocation has room for
+     */
+    uintptr_t capacity;
+} FfiVec_f64;
+
+/**
+ * Where rust put one of the `#[repr(C)]` types `C#` sees, or one of
+ * their fields, as handed out by `abi_layout_info`.
+ *
+ * None of the generic types change shape with `T`, since `T` is only
+ * ever behind a pointer, so each is described once. `BorrowedSlice` is
+ * the exception, whose size depends on its owner: it's described
+ * without one, which is the part `C#` reads.
+ */
+typedef struct FfiLayout {
+    /**
+     * The type, like `CSharpIteratorOut<T>`, as nul-terminated UTF-8
+     */
+    const uint8_t *ty;
+    /**
+     * The field, as nul-terminated UTF-8, or null for the type itself
+     */
+    const uint8_t *field;
+    /**
+     * The size of the type or field, in bytes
+     */
+    uintptr_t size;
+    /**
+     * The alignment of the type or field, in bytes
+     */
+    uintptr_t align;
+    /**
+     * Where the field starts, in bytes, or 0 for the type itself
+     */
+    uintptr_t offset;
+} FfiLayout;
+
+/**
+ * A string handed to `C#` as a pointer and a length in code units,
+ * either UTF-8 bytes (`FfiUtf8`) or UTF-16 chars (`FfiUtf16`, which is
+ * what `C#` strings are made of). There's no nul terminator.
+ *
+ * Each one is its own allocation, owned by whoever holds it: it stays
+ * valid until it's passed to `release_item` or to `ffi_utf8_free` /
+ * `ffi_utf16_free`, even after the iterator it came from is destroyed.
+ */
+typedef struct FfiString_u8 {
+    /**
+     * The pointer to the first code unit, dangling when `len` is 0
+     */
+    uint8_t *ptr;
+    /**
+     * How many code units there are
+     */
+    uintptr_t len;
+} FfiString_u8;
+
+typedef FfiString_u8 FfiUtf8;
+
+/**
+ * An example function:
+ *
+ * Creates an `Iterator<Item=FfiVec<usize>>` with each one counting up
+ * to the current iteration
+ *
+ * # Safety
+ * `cs` must be null or valid for writes.
+ */
+void get_iterator(CSharpIteratorOut_FfiVec_usize *cs);
+
+/**
+ * An example function:
+ *
+ * Creates an `Iterator<Item=FfiUtf16>` of lines, ready to be turned
+ * into `C#` strings
+ *
+ * # Safety
+ * `cs` must be null or valid for writes.
+ */
+void get_string_iterator(CSharpIteratorOut_FfiUtf16 *cs);
+
+/**
+ * An example function:
+ *
+ * Creates an endless `Iterator<Item=u64>` that takes a while per item,
+ * and stops once `token` is triggered
+ *
+ * # Safety
+ * `cs` must be null or valid for writes, and `token` must be null or
+ * come from `cancellation_token_new` and not have been freed yet.
+ */
+void get_slow_iterator(CSharpIteratorOut_u64 *cs, const CancellationToken *token);
+
+/**
+ * An example function:
+ *
+ * Creates a `Stream<Item=u64>` counting up to `count`, with an awaited
+ * pause before each number
+ *
+ * # Safety
+ * `cs` must be null or valid for writes.
+ */
+void get_ticking_stream(CSharpStreamOut_u64 *cs, uint64_t count);
+
+/**
+ * An example function:
+ *
+ * Counts from `start` up to `end` in steps of `step`, written with
+ * `#[export_iterator]` instead of by hand. A `step` of 0 panics, which
+ * `C#` finds out about through the last error
+ */
+bool get_range_iterator(CSharpIteratorOut_u64 *cs, uint64_t start, uint64_t end, uint64_t step);
+
+/**
+ * An example function:
+ *
+ * Splits a string from `C#` into words, ready to be turned back into
+ * `C#` strings
+ */
+bool get_word_iterator(CSharpIteratorOut_FfiUtf16 *cs, const uint8_t *text_ptr, uintptr_t text_len);
+
+/**
+ * An example function:
+ *
+ * Adds up a sequence of numbers that `C#` owns, wrapping on overflow
+ */
+uint64_t sum_foreign(ForeignIterator_u64 iter);
+
+/**
+ * An example function:
+ *
+ * Pushes the squares of `0..count` into a `C#` callback, stopping
+ * whenever it says so
+ */
+IterStatus push_squares(uint64_t count, CSharpSink_u64 sink);
+
+/**
+ * Creates a new token for `C#`, which has to be freed with
+ * `cancellation_token_free`.
+ */
+CancellationToken *cancellation_token_new(void);
+
+/**
+ * Triggers a token. Can be called from any thread, and does nothing
+ * when given a null pointer.
+ *
+ * # Safety
+ * `token` must be null or come from `cancellation_token_new` and not
+ * have been freed yet.
+ */
+void cancellation_token_cancel(const CancellationToken *token);
+
+/**
+ * Whether a token was triggered. A null token never is.
+ *
+ * # Safety
+ * `token` must be null or come from `cancellation_token_new` and not
+ * have been freed yet.
+ */
+bool cancellation_token_is_cancelled(const CancellationToken *token);
+
+/**
+ * Frees `C#`'s token. Iterators it was attached to keep working with
+ * their own clones.
+ *
+ * # Safety
+ * `token` must be null or come from `cancellation_token_new`, and must
+ * not be used again after this call.
+ */
+void cancellation_token_free(CancellationToken *token);
+
+/**
+ * Frees an `FfiVec<u8>` that was handed to `C#`.
+ */
+void ffi_vec_u8_free(FfiVec_u8 v);
+
+/**
+ * Frees an `FfiVec<u16>` that was handed to `C#`.
+ */
+void ffi_vec_u16_free(FfiVec_u16 v);
+
+/**
+ * Frees an `FfiVec<u32>` that was handed to `C#`.
+ */
+void ffi_vec_u32_free(FfiVec_u32 v);
+
+/**
+ * Frees an `FfiVec<u64>` that was handed to `C#`.
+ */
+void ffi_vec_u64_free(FfiVec_u64 v);
+
+/**
+ * Frees an `FfiVec<usize>` that was handed to `C#`.
+ */
+void ffi_vec_usize_free(FfiVec_usize v);
+
+/**
+ * Frees an `FfiVec<i8>` that was handed to `C#`.
+ */
+void ffi_vec_i8_free(FfiVec_i8 v);
+
+/**
+ * Frees an `FfiVec<i16>` that was handed to `C#`.
+ */
+void ffi_vec_i16_free(FfiVec_i16 v);
+
+/**
+ * Frees an `FfiVec<i32>` that was handed to `C#`.
+ */
+void ffi_vec_i32_free(FfiVec_i32 v);
+
+/**
+ * Frees an `FfiVec<i64>` that was handed to `C#`.
+ */
+void ffi_vec_i64_free(FfiVec_i64 v);
+
+/**
+ * Frees an `FfiVec<isize>` that was handed to `C#`.
+ */
+void ffi_vec_isize_free(FfiVec_isize v);
+
+/**
+ * Frees an `FfiVec<f32>` that was handed to `C#`.
+ */
+void ffi_vec_f32_free(FfiVec_f32 v);
+
+/**
+ * Frees an `FfiVec<f64>` that was handed to `C#`.
+ */
+void ffi_vec_f64_free(FfiVec_f64 v);
+
+/**
+ * The code of the last error on this thread: the `IterStatus` the failing
+ * call returned (or would have, for calls that don't return one), or
+ * `IterStatus::Item` if nothing failed since the last successful
+ * constructor call, or `destroy` of a `CSharpIteratorHandle`.
+ *
+ * Errors are recorded by every stock function that returns
+ * `IterStatus::Panicked`, `IterStatus::Error` or
+ * `IterStatus::InvalidHandle`, by `destroy` when the iterator panicked
+ * while being dropped, and by constructors written with `export_into`,
+ * like `get_iterator`. Nothing but those two clears it.
+ */
+IterStatus last_error_code(void);
+
+/**
+ * Copies the message of the last error on this thread into `buf` as
+ * UTF-8, writing at most `len` bytes, with no nul terminator. Returns
+ * the full length of the message in bytes, or 0 if there is none.
+ *
+ * To build an exception, call it once with a null `buf` to get the
+ * length, allocate that many bytes, and call it again to fill them.
+ *
+ * # Safety
+ * `buf` must be null or valid for `len` bytes of writes.
+ */
+uintptr_t last_error_message(uint8_t *buf, uintptr_t len);
+
+/**
+ * Copies the layout of every `#[repr(C)]` type `C#` sees into `buf`,
+ * writing at most `len` entries, and returns how many there are in
+ * total. A host can check these against its own definitions at startup,
+ * instead of finding out through corrupted items.
+ *
+ * Like `last_error_message`, call it once with a null `buf` to get the
+ * count, allocate that many entries, and call it again to fill them.
+ * The names point into static memory, and never have to be freed.
+ *
+ * # Safety
+ * `buf` must be null or valid for `len` entries of writes.
+ */
+uintptr_t abi_layout_info(FfiLayout *buf, uintptr_t len);
+
+/**
+ * Frees an `FfiUtf8` that was handed to `C#`.
+ */
+void ffi_utf8_free(FfiUtf8 s);
+
+/**
+ * Frees an `FfiUtf16` that was handed to `C#`.
+ */
+void ffi_utf16_free(FfiUtf16 s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,361 @@
+//! The `#[export_iterator]` attribute, re-exported by `cs_iter`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, FnArg, GenericArgument, Ident, ItemFn, Pat, PathArguments, ReturnType, Type, TypeParamBound, Visibility};
+
+/// Turns a function returning an iterator into one `C#` can call.
+///
+/// ```ignore
+/// #[export_iterator]
+/// pub fn get_lines(count: u32, prefix: String) -> impl Iterator<Item=FfiUtf16> {
+///     (0..count).map(move |x| format!("{} {}", prefix, x).into())
+/// }
+/// ```
+///
+/// becomes a `#[no_mangle] pub unsafe extern "C" fn get_lines(cs: *mut
+/// CSharpIteratorOut<FfiUtf16>, count: u32, prefix_ptr: *const u8,
+/// prefix_len: usize) -> bool`, which writes the iterator to `cs` and
+/// returns whether it did, the same as `try_export_into`. Arguments are
+/// passed on as they are, except for:
+///
+/// - `String`, which `C#` passes as a pointer to UTF-8 and a length
+/// - `Vec<T>`, which `C#` passes as a pointer to the first `T` and a length
+///
+/// Both are copied before the function runs, since the iterator outlives
+/// the call. A panic in the function, or an argument that doesn't make
+/// sense (like invalid UTF-8), is recorded as the last error.
+///
+/// The function has to return `impl Iterator<Item=T>`, or
+/// `impl ExactSizeIterator<Item=T>` to get an exact size hint.
+#[proc_macro_attribute]
+pub fn export_iterator(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let attr = TokenStream2::from(attr);
+        return Error::new(attr.span(), "`export_iterator` doesn't take any arguments").into_compile_error().into();
+    }
+    let func = parse_macro_input!(item as ItemFn);
+    expand(func).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// How an argument crosses over from `C#`
+enum Arg {
+    /// As it is
+    Plain(Ident, Type),
+    /// As a pointer to UTF-8 and a length
+    String(Ident),
+    /// As a pointer to the first element and a length
+    Vec(Ident, Type),
+}
+
+impl Arg {
+    fn from_input(input: &FnArg) -> syn::Result<Self> {
+        let input = match input {
+            FnArg::Typed(input) => input,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new(receiver.span(), "exported iterators can't take `self`"));
+            }
+        };
+        let name = match &*input.pat {
+            Pat::Ident(pat) => pat.ident.clone(),
+            pat => return Err(Error::new(pat.span(), "arguments of exported iterators have to be plain names")),
+        };
+        // The out pointer is called that
+        if name == "cs" {
+            return Err(Error::new(name.span(), "`cs` is taken by the out pointer"));
+        }
+        match &*input.ty {
+            Type::Reference(ty) => Err(Error::new(
+                ty.span(),
+                "the iterator outlives the call, so it can't borrow its arguments; take a `String` or `Vec<T>` instead",
+            )),
+            Type::Path(ty) if ty.qself.is_none() => {
+                let last = ty.path.segments.last().expect("paths have at least one segment");
+                match &last.arguments {
+                    PathArguments::None if last.ident == "String" => Ok(Arg::String(name)),
+                    PathArguments::AngleBracketed(args) if last.ident == "Vec" && args.args.len() == 1 => {
+                        match &args.args[0] {
+                            GenericArgument::Type(elem) => Ok(Arg::Vec(name, elem.clone())),
+                            _ => Ok(Arg::Plain(name, (*input.ty).clone())),
+                        }
+                    }
+                    _ => Ok(Arg::Plain(name, (*input.ty).clone())),
+                }
+            }
+            ty => Ok(Arg::Plain(name, ty.clone())),
+        }
+    }
+
+    /// The parameters of the exported function this turns into
+    fn params(&self) -> TokenStream2 {
+        match self {
+            Arg::Plain(name, ty) => quote!(#name: #ty),
+            Arg::String(name) => {
+                let (ptr, len) = split(name);
+                quote!(#ptr: *const u8, #len: usize)
+            }
+            Arg::Vec(name, elem) => {
+                let (ptr, len) = split(name);
+                quote!(#ptr: *const #elem, #len: usize)
+            }
+        }
+    }
+
+    /// Turns the parameters back into the argument, inside a closure
+    /// that returns `Result<_, String>`
+    fn unmarshal(&self) -> TokenStream2 {
+        match self {
+            Arg::Plain(..) => quote!(),
+            Arg::String(name) => {
+                let (ptr, len) = split(name);
+                let label = name.to_string();
+                quote!(let #name = ::cs_iter::string_arg(#ptr, #len, #label)?;)
+            }
+            Arg::Vec(name, _) => {
+                let (ptr, len) = split(name);
+                let label = name.to_string();
+                quote!(let #name = ::cs_iter::vec_arg(#ptr, #len, #label)?;)
+            }
+        }
+    }
+
+    fn name(&self) -> &Ident {
+        match self {
+            Arg::Plain(name, _) | Arg::String(name) | Arg::Vec(name, _) => name,
+        }
+    }
+}
+
+/// The pointer and length parameters an argument is split into
+fn split(name: &Ident) -> (Ident, Ident) {
+    (format_ident!("{}_ptr", name), format_ident!("{}_len", name))
+}
+
+/// Makes sure no argument has the name of a pointer or length another
+/// one is split into, the same way `cs` is taken by the out pointer
+fn check_split_names(args: &[Arg]) -> syn::Result<()> {
+    for arg in args {
+        if let Arg::String(split_name) | Arg::Vec(split_name, _) = arg {
+            let (ptr, len) = split(split_name);
+            for other in args {
+                let name = other.name();
+                let taken_by = if *name == ptr {
+                    "pointer"
+                } else if *name == len {
+                    "length"
+                } else {
+                    continue;
+                };
+                return Err(Error::new(name.span(), format!("`{}` is taken by the {} of `{}`", name, taken_by, split_name)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finds `T` in `-> impl Iterator<Item=T>`, along with whether it's an
+/// `ExactSizeIterator`
+fn iterator_item(output: &ReturnType) -> syn::Result<(Type, bool)> {
+    let expected = "exported iterators have to return `impl Iterator<Item=T>`";
+    let ty = match output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => return Err(Error::new(output.span(), expected)),
+    };
+    let bounds = match &**ty {
+        Type::ImplTrait(ty) => &ty.bounds,
+        ty => return Err(Error::new(ty.span(), expected)),
+    };
+    for bound in bounds {
+        let last = match bound {
+            TypeParamBound::Trait(bound) => bound.path.segments.last(),
+            _ => None,
+        };
+        let last = match last {
+            Some(last) if last.ident == "Iterator" || last.ident == "ExactSizeIterator" => last,
+            _ => continue,
+        };
+        if let PathArguments::AngleBracketed(args) = &last.arguments {
+            for arg in &args.args {
+                if let GenericArgument::AssocType(assoc) = arg {
+                    if assoc.ident == "Item" {
+                        return Ok((assoc.ty.clone(), last.ident == "ExactSizeIterator"));
+                    }
+                }
+            }
+        }
+    }
+    Err(Error::new(ty.span(), expected))
+}
+
+fn expand(func: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &func.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new(asyncness.span(), "exported iterators can't be `async`, return a `CSharpStreamOut` by hand instead"));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(Error::new(sig.generics.span(), "exported iterators can't be generic"));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new(variadic.span(), "exported iterators can't be variadic"));
+    }
+    let (item, exact) = iterator_item(&sig.output)?;
+    let args = sig.inputs.iter().map(Arg::from_input).collect::<syn::Result<Vec<_>>>()?;
+    check_split_names(&args)?;
+
+    let name = &sig.ident;
+    let vis = &func.vis;
+    let params = args.iter().map(Arg::params);
+    let unmarshal = args.iter().map(Arg::unmarshal);
+    let names = args.iter().map(Arg::name);
+    let form = if exact { quote!(form_exact) } else { quote!(form) };
+
+    // Docs and `cfg`s belong to the exported function, everything else
+    // to the original one, which lives on inside it
+    let (outer, inner): (Vec<_>, Vec<_>) = func.attrs.iter().cloned().partition(|attr| attr.path().is_ident("doc") || attr.path().is_ident("cfg"));
+    let mut original = func.clone();
+    original.attrs = inner;
+    original.vis = Visibility::Inherited;
+
+    Ok(quote! {
+        #(#outer)*
+        ///
+        /// # Safety
+        /// `cs` must be null or valid for writes, and every `_ptr` must be
+        /// null or valid for `_len` reads.
+        #[no_mangle]
+        #vis unsafe extern "C" fn #name(cs: *mut ::cs_iter::CSharpIteratorOut<#item>, #(#params),*) -> bool {
+            #original
+
+            ::cs_iter::try_export_into(cs, move || {
+                #(#unmarshal)*
+                ::std::result::Result::Ok::<_, ::std::string::String>(::cs_iter::CSharpIteratorOut::#form(#name(#(#names),*)))
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    /// The error `expand` gives for `func`
+    fn error(func: ItemFn) -> String {
+        expand(func).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn splits_arguments() {
+        let expanded: ItemFn = syn::parse2(
+            expand(parse_quote! {
+                /// Docs
+                pub fn words(text: String, lengths: Vec<u16>, count: u32) -> impl ExactSizeIterator<Item=u32> {
+                    std::iter::empty()
+                }
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let params: Vec<_> = expanded
+            .sig
+            .inputs
+            .iter()
+            .map(|input| match input {
+                FnArg::Typed(input) => quote!(#input).to_string(),
+                FnArg::Receiver(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            params,
+            [
+                "cs : * mut :: cs_iter :: CSharpIteratorOut < u32 >",
+                "text_ptr : * const u8",
+                "text_len : usize",
+                "lengths_ptr : * const u16",
+                "lengths_len : usize",
+                "count : u32",
+            ]
+        );
+        assert!(expanded.sig.unsafety.is_some());
+        assert_eq!(expanded.sig.abi.as_ref().and_then(|abi| abi.name.as_ref()).unwrap().value(), "C");
+        assert!(expanded.attrs.iter().any(|attr| attr.path().is_ident("no_mangle")));
+        assert!(expanded.attrs.iter().any(|attr| attr.path().is_ident("doc")));
+        // An exact size iterator gets an exact size hint
+        assert!(quote!(#expanded).to_string().contains("CSharpIteratorOut :: form_exact"));
+    }
+
+    #[test]
+    fn rejects_self() {
+        assert_eq!(
+            error(parse_quote!(fn f(self) -> impl Iterator<Item=u32> {})),
+            "exported iterators can't take `self`"
+        );
+    }
+
+    #[test]
+    fn rejects_async() {
+        assert_eq!(
+            error(parse_quote!(async fn f() -> impl Iterator<Item=u32> {})),
+            "exported iterators can't be `async`, return a `CSharpStreamOut` by hand instead"
+        );
+    }
+
+    #[test]
+    fn rejects_generics() {
+        assert_eq!(error(parse_quote!(fn f<T>() -> impl Iterator<Item=u32> {})), "exported iterators can't be generic");
+        assert_eq!(
+            error(parse_quote!(fn f() -> impl Iterator<Item=u32> where u32: Copy {})),
+            "exported iterators can't be generic"
+        );
+    }
+
+    #[test]
+    fn rejects_references() {
+        assert_eq!(
+            error(parse_quote!(fn f(text: &str) -> impl Iterator<Item=u32> {})),
+            "the iterator outlives the call, so it can't borrow its arguments; take a `String` or `Vec<T>` instead"
+        );
+    }
+
+    #[test]
+    fn rejects_cs() {
+        assert_eq!(error(parse_quote!(fn f(cs: u32) -> impl Iterator<Item=u32> {})), "`cs` is taken by the out pointer");
+    }
+
+    #[test]
+    fn rejects_split_names() {
+        assert_eq!(
+            error(parse_quote!(fn f(text: String, text_len: usize) -> impl Iterator<Item=u32> {})),
+            "`text_len` is taken by the length of `text`"
+        );
+        assert_eq!(
+            error(parse_quote!(fn f(items_ptr: u64, items: Vec<u8>) -> impl Iterator<Item=u32> {})),
+            "`items_ptr` is taken by the pointer of `items`"
+        );
+        assert_eq!(
+            error(parse_quote!(fn f(text: String, text_ptr: String) -> impl Iterator<Item=u32> {})),
+            "`text_ptr` is taken by the pointer of `text`"
+        );
+        // Anything else ending the same way is fine
+        assert!(expand(parse_quote!(fn f(text: String, other_len: usize) -> impl Iterator<Item=u32> {})).is_ok());
+    }
+
+    #[test]
+    fn rejects_non_iterators() {
+        let expected = "exported iterators have to return `impl Iterator<Item=T>`";
+        assert_eq!(error(parse_quote!(fn f() {})), expected);
+        assert_eq!(error(parse_quote!(fn f() -> Vec<u32> {})), expected);
+        assert_eq!(error(parse_quote!(fn f() -> impl Clone {})), expected);
+    }
+
+    #[test]
+    fn rejects_patterns() {
+        assert_eq!(
+            error(parse_quote!(fn f((a, b): (u32, u32)) -> impl Iterator<Item=u32> {})),
+            "arguments of exported iterators have to be plain names"
+        );
+    }
+}
@@ -0,0 +1,26 @@
+/// Copies a UTF-8 string argument out of the buffer `C#` passed, which is
+/// how `#[export_iterator]` takes `String`s. `name` is the argument's
+/// name, for the error message.
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes of reads.
+pub unsafe fn string_arg(ptr: *const u8, len: usize, name: &str) -> Result<String, String> {
+    let bytes = vec_arg(ptr, len, name)?;
+    String::from_utf8(bytes).map_err(|e| format!("argument `{}` isn't valid UTF-8: {}", name, e))
+}
+
+/// Copies an array argument out of the buffer `C#` passed, which is how
+/// `#[export_iterator]` takes `Vec`s. A null `ptr` is only fine when
+/// `len` is 0.
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` reads.
+pub unsafe fn vec_arg<T: Clone>(ptr: *const T, len: usize, name: &str) -> Result<Vec<T>, String> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if ptr.is_null() {
+        return Err(format!("argument `{}` is null", name));
+    }
+    Ok(std::slice::from_raw_parts(ptr, len).to_vec())
+}
@@ -0,0 +1,311 @@
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::last_error::{fail, NULL_DATA, NULL_ITERATOR};
+use crate::{message_impl_ffi, FfiSizeHint, IterState, IterStatus};
+
+/// The "iterator" we pass to `C#` when items should be lent out instead
+/// of handed over.
+///
+/// The current item stays inside the iterator's state, and
+/// `internal_iter` writes a pointer to it instead of the item itself.
+/// That pointer is valid until the next call to `internal_iter` or
+/// `destroy`, so `C#` copies out whatever it needs and never has to
+/// release anything. On any status other than `IterStatus::Item`, a null
+/// pointer is written.
+#[repr(C)]
+pub struct CSharpBorrowedIteratorOut<T> {
+    /// The function `C#` calls to move on to the next item and borrow it
+    pub internal_iter: unsafe extern "C" fn(*mut c_void, *mut *const T) -> IterStatus,
+    /// A thin pointer to the iterator's state that gets leaked
+    pub pointer: *mut c_void,
+    /// The function `C#` calls once it's done with the iterator, which
+    /// also drops the current item
+    pub destroy: unsafe extern "C" fn(*mut c_void),
+    /// The function `C#` calls to get the panic message after
+    /// `internal_iter` returned `IterStatus::Panicked`
+    pub message: unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> usize,
+    /// The function `C#` calls to find out how many items are left
+    pub size_hint: unsafe extern "C" fn(*mut c_void) -> FfiSizeHint,
+}
+
+/// A slice lent to `C#`, along with whatever owns it. `C#` only ever
+/// looks at `ptr` and `len`, which come first.
+#[repr(C)]
+pub struct BorrowedSlice<E, O> {
+    /// The pointer to the first element
+    pub(crate) ptr: *const E,
+    /// How many elements there are
+    pub(crate) len: usize,
+    /// What `ptr` points into, like a `Vec<E>` or a `String`
+    owner: O,
+}
+
+impl<E, O: AsRef<[E]>> BorrowedSlice<E, O> {
+    fn new(owner: O) -> Self {
+        BorrowedSlice {
+            ptr: std::ptr::null(),
+            len: 0,
+            owner,
+        }
+    }
+
+    /// Points `ptr` at the owner, once it's in its final place
+    fn settle(&mut self) {
+        let slice = self.owner.as_ref();
+        self.ptr = slice.as_ptr();
+        self.len = slice.len();
+    }
+}
+
+/// The state behind a `CSharpBorrowedIteratorOut`
+struct BorrowedState<I: Iterator> {
+    /// The state of the iterator itself
+    state: IterState<I>,
+    /// The item `C#` is currently borrowing
+    current: Option<I::Item>,
+    /// Fixes up the current item once it's in place, for items that
+    /// point into themselves
+    settle: fn(&mut I::Item),
+}
+
+impl<I: Iterator> BorrowedState<I> {
+    /// Drops the item `C#` was borrowing, recording a panic from its
+    /// destructor the same way `IterState::finish` does
+    fn drop_current(&mut self) {
+        if let Some(item) = self.current.take() {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| drop(item))) {
+                self.state.poison(&*payload);
+            }
+        }
+    }
+}
+
+/// A stock function that moves a borrowed iterator on, dropping the
+/// previous item and lending out the next one.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpBorrowedIteratorOut::form` and
+/// not have been passed to `destroy` yet, and `data` must be null or
+/// valid for writes.
+pub unsafe extern "C" fn borrowed_iter_impl_ffi<I: Iterator>(p: *mut c_void, data: *mut *const I::Item) -> IterStatus {
+    let p = p as *mut BorrowedState<I>;
+    if p.is_null() {
+        return fail(IterStatus::InvalidHandle, NULL_ITERATOR);
+    }
+    if data.is_null() {
+        return fail(IterStatus::Error, NULL_DATA);
+    }
+    let state = &mut *p;
+    // The previous item is only valid until now
+    state.drop_current();
+    match state.state.advance() {
+        Ok(x) => {
+            let current = state.current.insert(x);
+            let settle = state.settle;
+            // `settle` is user code as well
+            match catch_unwind(AssertUnwindSafe(|| settle(current))) {
+                Ok(()) => {
+                    *data = current as *const I::Item;
+                    IterStatus::Item
+                }
+                Err(payload) => {
+                    state.state.poison(&*payload);
+                    state.drop_current();
+                    *data = std::ptr::null();
+                    IterStatus::Panicked
+                }
+            }
+        }
+        Err(status) => {
+            *data = std::ptr::null();
+            status
+        }
+    }
+}
+
+/// A stock function that frees a borrowed iterator and its current item.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpBorrowedIteratorOut::form`, and
+/// must not be used again after this call.
+pub unsafe extern "C" fn borrowed_destroy_impl_ffi<I: Iterator>(p: *mut c_void) {
+    let p = p as *mut BorrowedState<I>;
+    if !p.is_null() {
+        let mut state = Box::from_raw(p);
+        state.drop_current();
+        state.state.finish();
+    }
+}
+
+/// A stock function that copies the panic message of a borrowed
+/// iterator, the same way as `message_impl_ffi`.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpBorrowedIteratorOut::form` and
+/// not have been passed to `destroy` yet, and `buf` must be null or valid
+/// for `len` bytes of writes.
+pub unsafe extern "C" fn borrowed_message_impl_ffi<I: Iterator>(p: *mut c_void, buf: *mut u8, len: usize) -> usize {
+    let p = p as *mut BorrowedState<I>;
+    if p.is_null() {
+        return 0;
+    }
+    // `state` is the first field, but don't rely on that
+    message_impl_ffi::<I>(&mut (*p).state as *mut IterState<I> as *mut c_void, buf, len)
+}
+
+/// A stock function that tells `C#` how many items a borrowed iterator
+/// has left, not counting the one it's currently borrowing.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpBorrowedIteratorOut::form` and
+/// not have been passed to `destroy` yet.
+pub unsafe extern "C" fn borrowed_size_hint_impl_ffi<I: Iterator>(p: *mut c_void) -> FfiSizeHint {
+    let p = p as *mut BorrowedState<I>;
+    if p.is_null() {
+        return FfiSizeHint::DONE;
+    }
+    (*p).state.size_hint()
+}
+
+impl<T> CSharpBorrowedIteratorOut<T> {
+    /// Creates a `CSharpBorrowedIteratorOut<T>` from an iterator over
+    /// `T`, lending each item to `C#` as it is
+    pub fn form<D: Iterator<Item=T> + 'static>(iter: D) -> Self {
+        Self::from_state(iter, |_| {})
+    }
+
+    fn from_state<D: Iterator<Item=T> + 'static>(iter: D, settle: fn(&mut T)) -> Self {
+        let state = BorrowedState {
+            state: IterState::new(iter),
+            current: None,
+            settle,
+        };
+        CSharpBorrowedIteratorOut {
+            internal_iter: borrowed_iter_impl_ffi::<D>,
+            pointer: Box::into_raw(Box::new(state)) as *mut c_void,
+            destroy: borrowed_destroy_impl_ffi::<D>,
+            message: borrowed_message_impl_ffi::<D>,
+            size_hint: borrowed_size_hint_impl_ffi::<D>,
+        }
+    }
+}
+
+impl<E: 'static, O: AsRef<[E]> + 'static> CSharpBorrowedIteratorOut<BorrowedSlice<E, O>> {
+    /// Creates a `CSharpBorrowedIteratorOut` from an iterator over
+    /// anything that can be looked at as a slice, like `Vec<E>` or
+    /// `String`, lending each one to `C#` as a pointer and a length
+    /// without copying it into an `FfiVec` first
+    pub fn form_slices<D: Iterator<Item=O> + 'static>(iter: D) -> Self {
+        Self::from_state(iter.map(BorrowedSlice::new), BorrowedSlice::settle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::common::{last_error, read_message};
+
+    /// Calls `internal_iter`, starting from a non-null pointer so a
+    /// written null shows up
+    fn borrow<T>(cs: &CSharpBorrowedIteratorOut<T>) -> (*const T, IterStatus) {
+        let mut data = std::ptr::NonNull::dangling().as_ptr() as *const T;
+        let status = unsafe { (cs.internal_iter)(cs.pointer, &mut data) };
+        (data, status)
+    }
+
+    /// Looks at the slice `C#` would see
+    unsafe fn slice<'a, E, O>(data: *const BorrowedSlice<E, O>) -> &'a [E] {
+        std::slice::from_raw_parts((*data).ptr, (*data).len)
+    }
+
+    /// Counts how many times it was dropped
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    /// Panics when dropped
+    struct Bomb;
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            panic!("drop");
+        }
+    }
+
+    #[test]
+    fn form_lends_each_item() {
+        let cs = CSharpBorrowedIteratorOut::form(vec![1u32, 2].into_iter());
+        let (first, status) = borrow(&cs);
+        assert_eq!(status, IterStatus::Item);
+        assert_eq!(unsafe { *first }, 1);
+        let (second, status) = borrow(&cs);
+        assert_eq!(status, IterStatus::Item);
+        assert_eq!(unsafe { *second }, 2);
+
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::Exhausted));
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::AlreadyFinished));
+        unsafe { (cs.destroy)(cs.pointer) };
+    }
+
+    #[test]
+    fn form_slices_lends_vecs_and_strings() {
+        let cs = CSharpBorrowedIteratorOut::form_slices(vec![vec![1u32, 2, 3], vec![]].into_iter());
+        let (data, status) = borrow(&cs);
+        assert_eq!(status, IterStatus::Item);
+        assert_eq!(unsafe { slice(data) }, [1, 2, 3]);
+        // Still valid until the next call
+        unsafe { (cs.size_hint)(cs.pointer) };
+        assert_eq!(unsafe { slice(data) }, [1, 2, 3]);
+        let (data, status) = borrow(&cs);
+        assert_eq!(status, IterStatus::Item);
+        assert_eq!(unsafe { slice(data) }, [] as [u32; 0]);
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::Exhausted));
+        unsafe { (cs.destroy)(cs.pointer) };
+
+        let words = vec!["grüße".to_string(), "🦀".to_string()];
+        let cs = CSharpBorrowedIteratorOut::form_slices(words.clone().into_iter());
+        for word in &words {
+            let (data, status) = borrow(&cs);
+            assert_eq!(status, IterStatus::Item);
+            assert_eq!(unsafe { slice(data) }, word.as_bytes());
+        }
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::Exhausted));
+        unsafe { (cs.destroy)(cs.pointer) };
+    }
+
+    #[test]
+    fn destroy_drops_the_current_item_once() {
+        let drops = Rc::new(Cell::new(0));
+        let items = vec![Counted(drops.clone()), Counted(drops.clone()), Counted(drops.clone())];
+        let cs = CSharpBorrowedIteratorOut::form(items.into_iter());
+        assert_eq!(borrow(&cs).1, IterStatus::Item);
+        assert_eq!(drops.get(), 0);
+        // Moving on drops the previous item
+        assert_eq!(borrow(&cs).1, IterStatus::Item);
+        assert_eq!(drops.get(), 1);
+        // The current item and the one never reached
+        unsafe { (cs.destroy)(cs.pointer) };
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn item_drop_panic_poisons() {
+        let cs = CSharpBorrowedIteratorOut::form(vec![Bomb, Bomb].into_iter());
+        assert_eq!(borrow(&cs).1, IterStatus::Item);
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::Panicked));
+        assert_eq!(last_error(), (IterStatus::Panicked, "drop".to_string()));
+        assert_eq!(read_message(|buf, len| unsafe { (cs.message)(cs.pointer, buf, len) }), "drop");
+        assert_eq!(borrow(&cs), (std::ptr::null(), IterStatus::Panicked));
+        // The `Bomb` left in the iterator goes off in `destroy`
+        unsafe { (cs.destroy)(cs.pointer) };
+        assert_eq!(last_error(), (IterStatus::Panicked, "drop".to_string()));
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{CSharpIteratorOut, IterState};
+
+/// A flag that stops an iterator from the outside, from any thread.
+///
+/// An iterator formed with `CSharpIteratorOut::form_cancellable` checks
+/// its token before and after every `next`, and returns
+/// `IterStatus::Cancelled` from then on. That can't interrupt a `next`
+/// that's stuck waiting on something, so sources that block for long
+/// should hold on to a clone of the token and check it themselves,
+/// returning `None` once it's triggered.
+///
+/// Clones share the same flag. `C#` gets its own through
+/// `cancellation_token_new`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token that hasn't been triggered yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Triggers the token. There's no way to take this back
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether this token, or any clone of it, was triggered
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> CSharpIteratorOut<T> {
+    /// Creates a `CSharpIteratorOut<T>` from an iterator over `T` that
+    /// stops as soon as `token` is triggered. The iterator keeps its own
+    /// clone of the token, so `C#` can free its one whenever
+    pub fn form_cancellable<D: Iterator<Item=T> + 'static>(iter: D, token: &CancellationToken) -> Self {
+        Self::from_state(IterState {
+            cancel: Some(token.clone()),
+            ..IterState::new(iter)
+        })
+    }
+}
+
+/// Creates a new token for `C#`, which has to be freed with
+/// `cancellation_token_free`.
+#[no_mangle]
+pub extern "C" fn cancellation_token_new() -> *mut CancellationToken {
+    Box::into_raw(Box::new(CancellationToken::new()))
+}
+
+/// Triggers a token. Can be called from any thread, and does nothing
+/// when given a null pointer.
+///
+/// # Safety
+/// `token` must be null or come from `cancellation_token_new` and not
+/// have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cancellation_token_cancel(token: *const CancellationToken) {
+    if let Some(token) = token.as_ref() {
+        token.cancel();
+    }
+}
+
+/// Whether a token was triggered. A null token never is.
+///
+/// # Safety
+/// `token` must be null or come from `cancellation_token_new` and not
+/// have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cancellation_token_is_cancelled(token: *const CancellationToken) -> bool {
+    token.as_ref().is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Frees `C#`'s token. Iterators it was attached to keep working with
+/// their own clones.
+///
+/// # Safety
+/// `token` must be null or come from `cancellation_token_new`, and must
+/// not be used again after this call.
+#[no_mangle]
+pub unsafe extern "C" fn cancellation_token_free(token: *mut CancellationToken) {
+    if !token.is_null() {
+        drop(Box::from_raw(token));
+    }
+}
@@ -0,0 +1,143 @@
+use std::ffi::c_void;
+
+use crate::last_error::{fail, NULL_DATA, NULL_ITERATOR};
+use crate::{
+    destroy_impl_ffi, iter_impl_ffi, message_impl_ffi, next_chunk_impl_ffi, release_item_impl_ffi,
+    size_hint_impl_ffi, CSharpIteratorOut, FfiSizeHint, IterState, IterStatus,
+};
+
+/// The "iterator" we pass to `C#` when it should be able to read from
+/// both ends.
+///
+/// It has the same fields as `CSharpIteratorOut`, plus `next_back`.
+/// Calls to `internal_iter` and `next_back` can be interleaved freely,
+/// and the iterator is only exhausted (and dropped) once the two ends
+/// meet, after which both return `IterStatus::AlreadyFinished`.
+#[repr(C)]
+pub struct CSharpDoubleEndedIteratorOut<T> {
+    /// The function `C#` calls to take an item off the front
+    pub internal_iter: unsafe extern "C" fn(*mut c_void, *mut T) -> IterStatus,
+    /// A thin pointer to the iterator's state that gets leaked
+    pub pointer: *mut c_void,
+    /// The function `C#` calls once it's done with the iterator
+    pub destroy: unsafe extern "C" fn(*mut c_void),
+    /// The function `C#` calls to get the panic message after either
+    /// end returned `IterStatus::Panicked`
+    pub message: unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> usize,
+    /// The function `C#` calls to hand an item back once it's done with it
+    pub release_item: unsafe extern "C" fn(*mut T),
+    /// The function `C#` calls to find out how many items are left
+    /// between the two ends
+    pub size_hint: unsafe extern "C" fn(*mut c_void) -> FfiSizeHint,
+    /// The function `C#` calls to take many items off the front at once
+    pub next_chunk: unsafe extern "C" fn(*mut c_void, *mut T, usize, *mut IterStatus) -> usize,
+    /// The function `C#` calls to take an item off the back
+    pub next_back: unsafe extern "C" fn(*mut c_void, *mut T) -> IterStatus,
+}
+
+impl<I: DoubleEndedIterator> IterState<I> {
+    /// Pulls the last item out of the iterator, the same way `advance`
+    /// pulls the first one
+    fn advance_back(&mut self) -> Result<I::Item, IterStatus> {
+        self.advance_with(DoubleEndedIterator::next_back)
+    }
+}
+
+/// A stock function that handles iterator work from the back, the same
+/// way `iter_impl_ffi` does from the front.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form_double_ended`
+/// and not have been passed to `destroy` yet, and `data` must be null or
+/// valid for writes.
+pub unsafe extern "C" fn next_back_impl_ffi<I: DoubleEndedIterator>(p: *mut c_void, data: *mut I::Item) -> IterStatus {
+    let p = p as *mut IterState<I>;
+    if p.is_null() {
+        return fail(IterStatus::InvalidHandle, NULL_ITERATOR);
+    }
+    // Don't pull an item out that we'd have nowhere to put
+    if data.is_null() {
+        return fail(IterStatus::Error, NULL_DATA);
+    }
+    match (*p).advance_back() {
+        Ok(x) => {
+            std::ptr::write(data, x);
+            IterStatus::Item
+        }
+        Err(status) => status,
+    }
+}
+
+impl<T> CSharpIteratorOut<T> {
+    /// Creates a `CSharpDoubleEndedIteratorOut<T>` from a double ended
+    /// iterator over `T`, so that `C#` can read it from either end
+    pub fn form_double_ended<D: DoubleEndedIterator<Item=T> + 'static>(iter: D) -> CSharpDoubleEndedIteratorOut<T> {
+        CSharpDoubleEndedIteratorOut {
+            internal_iter: iter_impl_ffi::<D>,
+            pointer: Box::into_raw(Box::new(IterState::new(iter))) as *mut c_void,
+            destroy: destroy_impl_ffi::<D>,
+            message: message_impl_ffi::<D>,
+            release_item: release_item_impl_ffi,
+            size_hint: size_hint_impl_ffi::<D>,
+            next_chunk: next_chunk_impl_ffi::<D>,
+            next_back: next_back_impl_ffi::<D>,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::*;
+
+    type Ends = unsafe extern "C" fn(*mut c_void, *mut u32) -> IterStatus;
+
+    /// Takes an item off the end `next` reads from
+    fn take(cs: &CSharpDoubleEndedIteratorOut<u32>, next: Ends) -> (Option<u32>, IterStatus) {
+        let mut item = MaybeUninit::uninit();
+        match unsafe { next(cs.pointer, item.as_mut_ptr()) } {
+            IterStatus::Item => (Some(unsafe { item.assume_init() }), IterStatus::Item),
+            status => (None, status),
+        }
+    }
+
+    fn chunk(cs: &CSharpDoubleEndedIteratorOut<u32>, len: usize) -> (Vec<u32>, IterStatus) {
+        let mut buf = vec![0; len];
+        let mut status = IterStatus::Item;
+        let written = unsafe { (cs.next_chunk)(cs.pointer, buf.as_mut_ptr(), len, &mut status) };
+        buf.truncate(written);
+        (buf, status)
+    }
+
+    fn assert_finished(cs: &CSharpDoubleEndedIteratorOut<u32>) {
+        assert_eq!(take(cs, cs.internal_iter), (None, IterStatus::AlreadyFinished));
+        assert_eq!(take(cs, cs.next_back), (None, IterStatus::AlreadyFinished));
+        assert_eq!(chunk(cs, 2), (vec![], IterStatus::AlreadyFinished));
+    }
+
+    #[test]
+    fn ends_meet_in_a_chunk() {
+        let cs = CSharpIteratorOut::form_double_ended((0..7).collect::<Vec<u32>>().into_iter());
+        assert_eq!(take(&cs, cs.internal_iter), (Some(0), IterStatus::Item));
+        assert_eq!(take(&cs, cs.next_back), (Some(6), IterStatus::Item));
+        assert_eq!(chunk(&cs, 2), (vec![1, 2], IterStatus::Item));
+        assert_eq!(take(&cs, cs.next_back), (Some(5), IterStatus::Item));
+        assert_eq!(take(&cs, cs.internal_iter), (Some(3), IterStatus::Item));
+        assert_eq!(chunk(&cs, 5), (vec![4], IterStatus::Exhausted));
+        assert_finished(&cs);
+        unsafe { (cs.destroy)(cs.pointer) };
+    }
+
+    #[test]
+    fn ends_meet_at_the_back() {
+        let cs = CSharpIteratorOut::form_double_ended(0..4u32);
+        assert_eq!(take(&cs, cs.internal_iter), (Some(0), IterStatus::Item));
+        assert_eq!(take(&cs, cs.next_back), (Some(3), IterStatus::Item));
+        assert_eq!(take(&cs, cs.internal_iter), (Some(1), IterStatus::Item));
+        assert_eq!(take(&cs, cs.next_back), (Some(2), IterStatus::Item));
+        assert_eq!(take(&cs, cs.next_back), (None, IterStatus::Exhausted));
+        assert_finished(&cs);
+        unsafe { (cs.destroy)(cs.pointer) };
+    }
+}
@@ -0,0 +1,189 @@
+use std::ffi::c_void;
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::last_error::{fail, NULL_DATA, NULL_ITERATOR};
+use crate::{
+    destroy_impl_ffi, message_impl_ffi, release_item_impl_ffi, size_hint_impl_ffi,
+    CSharpIteratorOut, IterState, IterStatus,
+};
+
+impl<I, T, E> IterState<I>
+where
+    I: Iterator<Item=Result<T, E>>,
+    E: Display,
+{
+    /// Pulls the next item out of an iterator over `Result`s, turning an
+    /// `Err` into `IterStatus::Error` and keeping its message around
+    fn advance_fallible(&mut self) -> Result<T, IterStatus> {
+        // The message only ever describes the latest item
+        self.error = None;
+        match self.advance()? {
+            Ok(x) => Ok(x),
+            Err(e) => match catch_unwind(AssertUnwindSafe(move || e.to_string())) {
+                Ok(message) => {
+                    fail(IterStatus::Error, message.as_str());
+                    self.error = Some(message);
+                    Err(IterStatus::Error)
+                }
+                // `Display` is user code as well
+                Err(payload) => {
+                    self.poison(&*payload);
+                    Err(IterStatus::Panicked)
+                }
+            },
+        }
+    }
+}
+
+/// A stock function that handles iterator work for iterators over
+/// `Result<T, E>`, the same way `iter_impl_ffi` does for iterators over
+/// `T`. An `Err` item writes nothing to `data` and returns
+/// `IterStatus::Error`, after which `message` gives the error's message
+/// and the iterator can still be polled for the items after it.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form_fallible` and
+/// not have been passed to `destroy_impl_ffi` yet, and `data` must be
+/// null or valid for writes.
+pub unsafe extern "C" fn fallible_iter_impl_ffi<I, T, E>(p: *mut c_void, data: *mut T) -> IterStatus
+where
+    I: Iterator<Item=Result<T, E>>,
+    E: Display,
+{
+    let p = p as *mut IterState<I>;
+    if p.is_null() {
+        return fail(IterStatus::InvalidHandle, NULL_ITERATOR);
+    }
+    if data.is_null() {
+        return fail(IterStatus::Error, NULL_DATA);
+    }
+    match (*p).advance_fallible() {
+        Ok(x) => {
+            std::ptr::write(data, x);
+            IterStatus::Item
+        }
+        Err(status) => status,
+    }
+}
+
+/// A stock function that handles iterator work in bulk for iterators over
+/// `Result<T, E>`, the same way `next_chunk_impl_ffi` does. The chunk
+/// stops at the first `Err` item, with `IterStatus::Error` as its status.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form_fallible` and
+/// not have been passed to `destroy_impl_ffi` yet, `buf` must be null or
+/// valid for `len` writes, and `status` must be null or valid for a write.
+pub unsafe extern "C" fn fallible_next_chunk_impl_ffi<I, T, E>(p: *mut c_void, buf: *mut T, len: usize, status: *mut IterStatus) -> usize
+where
+    I: Iterator<Item=Result<T, E>>,
+    E: Display,
+{
+    let p = p as *mut IterState<I>;
+    let (written, result) = if p.is_null() {
+        (0, fail(IterStatus::InvalidHandle, NULL_ITERATOR))
+    } else if buf.is_null() && len != 0 {
+        (0, fail(IterStatus::Error, NULL_DATA))
+    } else {
+        let mut written = 0;
+        let mut result = IterStatus::Item;
+        while written < len {
+            match (*p).advance_fallible() {
+                Ok(x) => std::ptr::write(buf.add(written), x),
+                Err(status) => {
+                    result = status;
+                    break;
+                }
+            }
+            written += 1;
+        }
+        (written, result)
+    };
+    if !status.is_null() {
+        *status = result;
+    }
+    written
+}
+
+impl<T> CSharpIteratorOut<T> {
+    /// Creates a `CSharpIteratorOut<T>` from an iterator over
+    /// `Result<T, E>`, like a file reader or a parser. `Ok` items are
+    /// handed to `C#` as usual, while `Err` items make `internal_iter`
+    /// return `IterStatus::Error` and leave their message for `message`,
+    /// so that `C#` can decide whether to keep going or stop.
+    pub fn form_fallible<E, D>(iter: D) -> Self
+    where
+        E: Display,
+        D: Iterator<Item=Result<T, E>> + 'static,
+    {
+        CSharpIteratorOut {
+            internal_iter: fallible_iter_impl_ffi::<D, T, E>,
+            pointer: Box::into_raw(Box::new(IterState::new(iter))) as *mut c_void,
+            destroy: destroy_impl_ffi::<D>,
+            message: message_impl_ffi::<D>,
+            release_item: release_item_impl_ffi,
+            size_hint: size_hint_impl_ffi::<D>,
+            next_chunk: fallible_next_chunk_impl_ffi::<D, T, E>,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+    use crate::common::{destroy, last_error, message, next, next_chunk};
+
+    /// An error whose `Display` panics
+    struct Loud;
+
+    impl Display for Loud {
+        fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+            panic!("display")
+        }
+    }
+
+    fn parse(words: &'static [&'static str]) -> CSharpIteratorOut<u32> {
+        CSharpIteratorOut::form_fallible(words.iter().map(|word| word.parse::<u32>()))
+    }
+
+    #[test]
+    fn err_item_is_an_error() {
+        let cs = parse(&["1", "x", "3"]);
+        assert_eq!(next(&cs), (Some(1), IterStatus::Item));
+        assert_eq!(message(&cs), "");
+
+        assert_eq!(next(&cs), (None, IterStatus::Error));
+        let text = "x".parse::<u32>().unwrap_err().to_string();
+        assert_eq!(message(&cs), text);
+        assert_eq!(last_error(), (IterStatus::Error, text));
+
+        // The items after it still come through, and clear the message
+        assert_eq!(next(&cs), (Some(3), IterStatus::Item));
+        assert_eq!(message(&cs), "");
+        assert_eq!(next(&cs), (None, IterStatus::Exhausted));
+        destroy(&cs);
+    }
+
+    #[test]
+    fn chunk_stops_at_err() {
+        let cs = parse(&["1", "2", "x", "4"]);
+        assert_eq!(next_chunk(&cs, 4), (vec![1, 2], IterStatus::Error));
+        assert_eq!(message(&cs), "x".parse::<u32>().unwrap_err().to_string());
+        assert_eq!(next_chunk(&cs, 4), (vec![4], IterStatus::Exhausted));
+        destroy(&cs);
+    }
+
+    #[test]
+    fn display_panic_poisons() {
+        let cs = CSharpIteratorOut::<u32>::form_fallible(vec![Ok(1), Err(Loud), Ok(3)].into_iter());
+        assert_eq!(next(&cs), (Some(1), IterStatus::Item));
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        assert_eq!(message(&cs), "display");
+        assert_eq!(last_error(), (IterStatus::Panicked, "display".to_string()));
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        destroy(&cs);
+    }
+}
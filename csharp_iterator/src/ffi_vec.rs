@@ -0,0 +1,157 @@
+use std::iter::FromIterator;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+
+/// A `Vec<T>` with a layout we promise to keep, unlike `Vec<T>` itself
+/// whose field order is up to the compiler. This is what collections
+/// should be sent to `C#` as.
+#[repr(C)]
+pub struct FfiVec<T> {
+    /// The pointer to the first element, dangling when `capacity` is 0
+    pub(crate) ptr: *mut T,
+    /// How many elements are initialized
+    pub(crate) len: usize,
+    /// How many elements the allocation has room for
+    pub(crate) capacity: usize,
+}
+
+// Same as `Vec<T>`, since that's all this is
+unsafe impl<T: Send> Send for FfiVec<T> {}
+unsafe impl<T: Sync> Sync for FfiVec<T> {}
+
+impl<T> From<Vec<T>> for FfiVec<T> {
+    fn from(v: Vec<T>) -> Self {
+        // The `FfiVec` takes over the allocation
+        let mut v = ManuallyDrop::new(v);
+        FfiVec {
+            ptr: v.as_mut_ptr(),
+            len: v.len(),
+            capacity: v.capacity(),
+        }
+    }
+}
+
+impl<T> From<FfiVec<T>> for Vec<T> {
+    fn from(v: FfiVec<T>) -> Self {
+        // And the `Vec` takes it back
+        let v = ManuallyDrop::new(v);
+        unsafe { Vec::from_raw_parts(v.ptr, v.len, v.capacity) }
+    }
+}
+
+impl<T> FromIterator<T> for FfiVec<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<T>>().into()
+    }
+}
+
+impl<T> Drop for FfiVec<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Vec::from_raw_parts(self.ptr, self.len, self.capacity)) }
+    }
+}
+
+impl<T> Default for FfiVec<T> {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+impl<T> Deref for FfiVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<T> DerefMut for FfiVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for FfiVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Exports a function that frees an `FfiVec` per element type, for when
+/// `C#` doesn't have the iterator's `release_item` at hand
+macro_rules! ffi_vec_free {
+    ($($name:ident: $t:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Frees an `FfiVec<", stringify!($t), ">` that was handed to `C#`.")]
+            #[no_mangle]
+            pub extern "C" fn $name(v: FfiVec<$t>) {
+                drop(v);
+            }
+        )*
+    };
+}
+
+ffi_vec_free! {
+    ffi_vec_u8_free: u8,
+    ffi_vec_u16_free: u16,
+    ffi_vec_u32_free: u32,
+    ffi_vec_u64_free: u64,
+    ffi_vec_usize_free: usize,
+    ffi_vec_i8_free: i8,
+    ffi_vec_i16_free: i16,
+    ffi_vec_i32_free: i32,
+    ffi_vec_i64_free: i64,
+    ffi_vec_isize_free: isize,
+    ffi_vec_f32_free: f32,
+    ffi_vec_f64_free: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{collect, destroy};
+    use crate::{get_iterator, CSharpIteratorOut, IterStatus};
+
+    #[test]
+    fn round_trip_keeps_the_allocation() {
+        let mut v = Vec::with_capacity(8);
+        v.extend([1u32, 2, 3]);
+        let (ptr, len, capacity) = (v.as_ptr(), v.len(), v.capacity());
+
+        let ffi = FfiVec::from(v);
+        assert_eq!((ffi.ptr as *const u32, ffi.len, ffi.capacity), (ptr, len, capacity));
+        assert_eq!(*ffi, [1, 2, 3]);
+
+        let v = Vec::from(ffi);
+        assert_eq!((v.as_ptr(), v.len(), v.capacity()), (ptr, len, capacity));
+        assert_eq!(v, [1, 2, 3]);
+
+        let empty = FfiVec::<u32>::default();
+        assert_eq!((empty.len, empty.capacity), (0, 0));
+        assert!(Vec::from(empty).is_empty());
+    }
+
+    #[test]
+    fn get_iterator_yields_ranges() {
+        let mut cs = std::mem::MaybeUninit::<CSharpIteratorOut<FfiVec<usize>>>::uninit();
+        unsafe { get_iterator(cs.as_mut_ptr()) };
+        let cs = unsafe { cs.assume_init() };
+        let (items, status) = collect(&cs);
+        assert_eq!(status, IterStatus::Exhausted);
+        destroy(&cs);
+        assert_eq!(items.len(), 40);
+        for (x, item) in items.iter().enumerate() {
+            assert_eq!(**item, (0..x).collect::<Vec<_>>()[..]);
+        }
+    }
+
+    #[test]
+    fn free_takes_the_vec_back() {
+        let v: FfiVec<u32> = (0..100).collect();
+        assert_eq!(v.len, 100);
+        // Goes through `Vec::from_raw_parts`, so miri or a sanitizer
+        // would catch a mismatched layout here
+        ffi_vec_u32_free(v);
+        ffi_vec_u32_free(FfiVec::default());
+    }
+}
@@ -0,0 +1,63 @@
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+
+/// A sequence owned by `C#` (like an `IEnumerable<T>`), handed to rust as
+/// a context pointer and a pair of callbacks. It's a plain `Iterator`, so
+/// rust functions exported by this crate can take one and use all the
+/// usual combinators on it.
+///
+/// Rust calls `next` until it returns false, and never after that, then
+/// calls `release` exactly once when it drops the iterator, whether or
+/// not it read it to the end.
+#[repr(C)]
+pub struct ForeignIterator<T> {
+    /// Passed back to `next` and `release`, see [Callbacks](crate#callbacks)
+    pub(crate) context: *mut c_void,
+    /// Writes the next item to the slot and returns true, or returns false
+    /// once there are no more items. The slot is uninitialized until it's
+    /// written to, and rust owns the item afterwards
+    pub(crate) next: unsafe extern "C" fn(*mut c_void, *mut T) -> bool,
+    /// Lets `C#` clean up its side, can be null if there's nothing to do
+    pub(crate) release: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+/// Takes the place of `next` once the sequence ran out, so that `C#`
+/// isn't asked again
+unsafe extern "C" fn finished_impl_ffi<T>(_context: *mut c_void, _data: *mut T) -> bool {
+    false
+}
+
+impl<T> ForeignIterator<T> {
+    /// See [Callbacks](crate#callbacks).
+    ///
+    /// # Safety
+    /// `next` must keep its promises for as long as the iterator lives,
+    /// and `context` must stay valid until `release` is called.
+    pub unsafe fn new(context: *mut c_void, next: unsafe extern "C" fn(*mut c_void, *mut T) -> bool, release: Option<unsafe extern "C" fn(*mut c_void)>) -> Self {
+        ForeignIterator { context, next, release }
+    }
+}
+
+impl<T> Iterator for ForeignIterator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mut slot = MaybeUninit::<T>::uninit();
+        unsafe {
+            if (self.next)(self.context, slot.as_mut_ptr()) {
+                Some(slot.assume_init())
+            } else {
+                self.next = finished_impl_ffi;
+                None
+            }
+        }
+    }
+}
+
+impl<T> Drop for ForeignIterator<T> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            unsafe { release(self.context) }
+        }
+    }
+}
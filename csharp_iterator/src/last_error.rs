@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{copy_to_buffer, panic_message, IterStatus};
+
+thread_local! {
+    /// Why the last failing call on this thread failed
+    static LAST_ERROR: RefCell<Option<(IterStatus, String)>> = const { RefCell::new(None) };
+}
+
+/// The message for a null iterator pointer
+pub(crate) const NULL_ITERATOR: &str = "the iterator pointer is null";
+/// The message for a null data pointer or buffer
+pub(crate) const NULL_DATA: &str = "the data pointer is null";
+/// The message for a null out pointer passed to a constructor
+pub(crate) const NULL_OUT: &str = "the out pointer is null";
+/// The message for a handle that isn't in the registry
+pub(crate) const INVALID_HANDLE: &str = "the handle was already destroyed, or never existed";
+/// The message for asking a stream for an item while it's still busy
+/// with the last request
+pub(crate) const REQUEST_PENDING: &str = "the last request hasn't completed yet";
+
+/// Records why a call failed, for `last_error_code` and
+/// `last_error_message`, and hands the status back
+pub(crate) fn fail(code: IterStatus, message: impl Into<String>) -> IterStatus {
+    let message = message.into();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some((code, message)));
+    code
+}
+
+/// Forgets the last error, once a call that can fail succeeded
+pub(crate) fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// The code of the last error on this thread: the `IterStatus` the failing
+/// call returned (or would have, for calls that don't return one), or
+/// `IterStatus::Item` if nothing failed since the last successful
+/// constructor call, or `destroy` of a `CSharpIteratorHandle`.
+///
+/// Errors are recorded by every stock function that returns
+/// `IterStatus::Panicked`, `IterStatus::Error` or
+/// `IterStatus::InvalidHandle`, by `destroy` when the iterator panicked
+/// while being dropped, and by constructors written with `export_into`,
+/// like `get_iterator`. Nothing but those two clears it.
+#[no_mangle]
+pub extern "C" fn last_error_code() -> IterStatus {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(IterStatus::Item, |(code, _)| *code))
+}
+
+/// Copies the message of the last error on this thread into `buf` as
+/// UTF-8, writing at most `len` bytes, with no nul terminator. Returns
+/// the full length of the message in bytes, or 0 if there is none.
+///
+/// To build an exception, call it once with a null `buf` to get the
+/// length, allocate that many bytes, and call it again to fill them.
+///
+/// # Safety
+/// `buf` must be null or valid for `len` bytes of writes.
+#[no_mangle]
+pub unsafe extern "C" fn last_error_message(buf: *mut u8, len: usize) -> usize {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some((_, message)) => copy_to_buffer(message, buf, len),
+        None => 0,
+    })
+}
+
+/// Writes the result of `make` to an out pointer from `C#`, which is how
+/// exported constructors like `get_iterator` should be written.
+///
+/// A null `out` or a panic in `make` is recorded as the last error and
+/// leaves `out` untouched, otherwise the last error is cleared. Returns
+/// whether `out` was written.
+///
+/// # Safety
+/// `out` must be null or valid for writes. Whatever it pointed to before
+/// is overwritten without being dropped.
+pub unsafe fn export_into<S>(out: *mut S, make: impl FnOnce() -> S) -> bool {
+    try_export_into(out, || Ok::<S, String>(make()))
+}
+
+/// Like `export_into`, but `make` can fail as well, like when an argument
+/// from `C#` doesn't make sense. An `Err` is recorded as the last error
+/// with `IterStatus::Error` and leaves `out` untouched.
+///
+/// # Safety
+/// Same as `export_into`.
+pub unsafe fn try_export_into<S, E: Display>(out: *mut S, make: impl FnOnce() -> Result<S, E>) -> bool {
+    if out.is_null() {
+        fail(IterStatus::Error, NULL_OUT);
+        return false;
+    }
+    // Formatting the error is user code too
+    match catch_unwind(AssertUnwindSafe(|| make().map_err(|e| e.to_string()))) {
+        Ok(Ok(value)) => {
+            std::ptr::write(out, value);
+            clear_last_error();
+            true
+        }
+        Ok(Err(message)) => {
+            fail(IterStatus::Error, message);
+            false
+        }
+        Err(payload) => {
+            fail(IterStatus::Panicked, panic_message(&*payload));
+            false
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::ffi::CStr;
+use std::mem::{align_of, size_of};
+
+use crate::{
+    BorrowedSlice, CSharpBorrowedIteratorOut, CSharpCompletion, CSharpDoubleEndedIteratorOut, CSharpIteratorHandle, CSharpIteratorOut, CSharpSink,
+    CSharpStreamOut, FfiSizeHint, FfiString, FfiVec, ForeignIterator, IterStatus,
+};
+
+/// Where rust put one of the `#[repr(C)]` types `C#` sees, or one of
+/// their fields, as handed out by `abi_layout_info`.
+///
+/// None of the generic types change shape with `T`, since `T` is only
+/// ever behind a pointer, so each is described once. `BorrowedSlice` is
+/// the exception, whose size depends on its owner: it's described
+/// without one, which is the part `C#` reads.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiLayout {
+    /// The type, like `CSharpIteratorOut<T>`, as nul-terminated UTF-8
+    ty: *const u8,
+    /// The field, as nul-terminated UTF-8, or null for the type itself
+    field: *const u8,
+    /// The size of the type or field, in bytes
+    size: usize,
+    /// The alignment of the type or field, in bytes
+    align: usize,
+    /// Where the field starts, in bytes, or 0 for the type itself
+    offset: usize,
+}
+
+impl FfiLayout {
+    /// The name of the type this describes
+    pub fn ty(&self) -> &'static str {
+        // These only ever come from the table below
+        unsafe { CStr::from_ptr(self.ty.cast()) }.to_str().unwrap()
+    }
+
+    /// The name of the field this describes, if it's not the type itself
+    pub fn field(&self) -> Option<&'static str> {
+        if self.field.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(self.field.cast()) }.to_str().unwrap())
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// The size and alignment of whatever the pointer `field` returns points
+/// to, without ever calling it
+fn field_layout<T, F>(_field: fn(*const T) -> *const F) -> (usize, usize) {
+    (size_of::<F>(), align_of::<F>())
+}
+
+macro_rules! layouts {
+    ($($name:literal: $ty:ty { $($field:ident),* })*) => {
+        vec![$(
+            FfiLayout {
+                ty: concat!($name, "\0").as_ptr(),
+                field: std::ptr::null(),
+                size: size_of::<$ty>(),
+                align: align_of::<$ty>(),
+                offset: 0,
+            },
+            $({
+                // `addr_of!` never reads through the pointer
+                let (size, align) = field_layout(|ty: *const $ty| unsafe { std::ptr::addr_of!((*ty).$field) });
+                FfiLayout {
+                    ty: concat!($name, "\0").as_ptr(),
+                    field: concat!(stringify!($field), "\0").as_ptr(),
+                    size,
+                    align,
+                    offset: std::mem::offset_of!($ty, $field),
+                }
+            },)*
+        )*]
+    };
+}
+
+/// Every type `C#` sees, followed by each of its fields in order
+pub fn abi_layout() -> Vec<FfiLayout> {
+    layouts! {
+        "IterStatus": IterStatus {}
+        "FfiSizeHint": FfiSizeHint { lower, upper, has_upper, exact }
+        "FfiVec<T>": FfiVec<u64> { ptr, len, capacity }
+        "FfiString<C>": FfiString<u16> { ptr, len }
+        "CSharpIteratorOut<T>": CSharpIteratorOut<u64> { internal_iter, pointer, destroy, message, release_item, size_hint, next_chunk }
+        "CSharpDoubleEndedIteratorOut<T>": CSharpDoubleEndedIteratorOut<u64> {
+            internal_iter, pointer, destroy, message, release_item, size_hint, next_chunk, next_back
+        }
+        "CSharpBorrowedIteratorOut<T>": CSharpBorrowedIteratorOut<u64> { internal_iter, pointer, destroy, message, size_hint }
+        "BorrowedSlice<E>": BorrowedSlice<u64, ()> { ptr, len }
+        "CSharpIteratorHandle<T>": CSharpIteratorHandle<u64> { internal_iter, handle, destroy, message, release_item, size_hint, next_chunk }
+        "ForeignIterator<T>": ForeignIterator<u64> { context, next, release }
+        "CSharpSink<T>": CSharpSink<u64> { context, push }
+        "CSharpStreamOut<T>": CSharpStreamOut<u64> { request_next, pointer, destroy, message, release_item }
+        "CSharpCompletion": CSharpCompletion { context, complete }
+        "FfiLayout": FfiLayout { ty, field, size, align, offset }
+    }
+}
+
+/// Copies the layout of every `#[repr(C)]` type `C#` sees into `buf`,
+/// writing at most `len` entries, and returns how many there are in
+/// total. A host can check these against its own definitions at startup,
+/// instead of finding out through corrupted items.
+///
+/// Like `last_error_message`, call it once with a null `buf` to get the
+/// count, allocate that many entries, and call it again to fill them.
+/// The names point into static memory, and never have to be freed.
+///
+/// # Safety
+/// `buf` must be null or valid for `len` entries of writes.
+#[no_mangle]
+pub unsafe extern "C" fn abi_layout_info(buf: *mut FfiLayout, len: usize) -> usize {
+    let layouts = abi_layout();
+    if !buf.is_null() {
+        std::ptr::copy_nonoverlapping(layouts.as_ptr(), buf, layouts.len().min(len));
+    }
+    layouts.len()
+}
@@ -1,55 +1,924 @@
-/// The "iterator" we pass to `C#`
+//! Rust iterators for `C#`, and `C#` sequences for rust.
+//!
+//! # Callbacks
+//!
+//! Where `C#` hands rust callbacks instead of the other way around
+//! (`ForeignIterator`, `CSharpSink` and `CSharpCompletion`), they come
+//! with a `context` pointer: whatever `C#` needs to find its side again,
+//! like a `GCHandle`. Rust never looks at it, it just passes it back as
+//! the first argument of every callback.
+//!
+//! Each of these has an unsafe `new`, which puts one together by hand
+//! the way `C#` would, so rust can test the functions taking them.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use last_error::{fail, NULL_DATA, NULL_ITERATOR};
+
+// So that `#[export_iterator]` works in here too
+extern crate self as cs_iter;
+
+mod args;
+mod borrowed;
+mod cancel;
+mod double_ended;
+mod fallible;
+mod ffi_vec;
+mod foreign;
+mod last_error;
+mod layout;
+mod prefetch;
+mod registry;
+mod sink;
+mod stream;
+mod strings;
+mod trace;
+
+pub use args::{string_arg, vec_arg};
+pub use borrowed::{BorrowedSlice, CSharpBorrowedIteratorOut};
+pub use cancel::CancellationToken;
+pub use cs_iter_macros::export_iterator;
+pub use double_ended::CSharpDoubleEndedIteratorOut;
+pub use ffi_vec::FfiVec;
+pub use foreign::ForeignIterator;
+pub use last_error::{export_into, last_error_code, last_error_message, try_export_into};
+pub use layout::{abi_layout, abi_layout_info, FfiLayout};
+pub use registry::CSharpIteratorHandle;
+pub use sink::{drive_into_callback, CSharpSink};
+pub use stream::{block_on, CSharpCompletion, CSharpStreamOut};
+pub use strings::{ffi_utf16_free, ffi_utf8_free, FfiString, FfiUtf16, FfiUtf8};
+pub use trace::Traced;
+
+/// What `internal_iter` tells `C#` after each call.
+///
+/// `AlreadyFinished`, `Panicked` and `Cancelled` are sticky: once an
+/// iterator returns one of them, every later call returns the same
+/// thing. `Exhausted` is only ever returned once, and is followed by
+/// `AlreadyFinished`. `Item`, `Error` and `InvalidHandle` say nothing
+/// about the next call.
 #[repr(C)]
-pub struct CSharpIteratorOut<T: Sized + Default> {
-    /// The function we pass `C#`. It's called by `C#` and recieves the 
-    /// pointer to the `Box`ed iterator
-    internal_iter: extern "C" fn(*mut Box<dyn Iterator<Item=T>>, *mut T) -> bool,
-    /// A thin pointer to the fat iterator pointer that gets leaked
-    pointer: *mut Box<dyn Iterator<Item=T>>
-}
-
-/// A stock function that handles iterator work. This is unsafe.
-pub extern fn iter_impl_ffi<T: Sized + Default + std::fmt::Debug>(p: *mut Box<dyn Iterator<Item=T>>, data: *mut T) -> bool{
-    unsafe {
-        match (*p).next() {
-            // If there is new data...
-            Some(x) => {
-                // Write it to the pointer we got...
-                *data = x;
-                // And tell `C#` that it can poll again
-                true
-            }
-            // If there isn't any new data...
-            None => {
-                // Drop iterator automatically, C# will have to sanity check
-                let _ = Box::from_raw(p); 
-                // And tell `C#` to not poll again
-                false
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IterStatus {
+    /// There was new data, and it was written to the data pointer
+    Item = 0,
+    /// The iterator just ran out of data, and was dropped
+    Exhausted = 1,
+    /// The iterator ran out of data on an earlier call
+    AlreadyFinished = 2,
+    /// The iterator panicked, either on this call or on an earlier one.
+    /// The iterator isn't touched anymore, and the panic message can be
+    /// fetched through `message`
+    Panicked = 3,
+    /// Either the call itself was wrong (like a null data pointer), or
+    /// the item was an error (see `form_fallible`), whose message can be
+    /// fetched through `message`. Either way, the iterator can still be
+    /// polled
+    Error = 4,
+    /// The iterator pointer doesn't point to an iterator
+    InvalidHandle = 5,
+    /// The iterator's `CancellationToken` was triggered, either during
+    /// this call or before it. The iterator was dropped, and any item it
+    /// produced in the meantime along with it
+    Cancelled = 6,
+}
+
+/// How many items an iterator has left, as told to `C#` by `size_hint`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FfiSizeHint {
+    /// The least number of items left
+    pub lower: usize,
+    /// The most number of items left, only meaningful if `has_upper` is set
+    pub upper: usize,
+    /// Whether there is an upper bound at all
+    pub has_upper: bool,
+    /// Whether `lower` is exactly the number of items left, which is
+    /// only promised for iterators made with `form_exact`, and once an
+    /// iterator is done
+    pub exact: bool,
+}
+
+impl FfiSizeHint {
+    /// The size hint of an iterator that won't yield anything anymore
+    const DONE: Self = FfiSizeHint {
+        lower: 0,
+        upper: 0,
+        has_upper: true,
+        exact: true,
+    };
+}
+
+/// The state that lives behind `CSharpIteratorOut::pointer`
+pub(crate) struct IterState<I> {
+    /// The iterator itself, dropped as soon as it finishes or panics
+    iter: Option<I>,
+    /// The message of the panic that poisoned this iterator, if any
+    panic: Option<String>,
+    /// The message of the error the last item was, if any
+    error: Option<String>,
+    /// Whether the iterator is an `ExactSizeIterator`
+    exact: bool,
+    /// The token that stops the iterator, if it was formed with one
+    cancel: Option<CancellationToken>,
+    /// Whether the token stopped it
+    cancelled: bool,
+}
+
+impl<I: Iterator> IterState<I> {
+    fn new(iter: I) -> Self {
+        IterState {
+            iter: Some(iter),
+            panic: None,
+            error: None,
+            exact: false,
+            cancel: None,
+            cancelled: false,
+        }
+    }
+
+    fn new_exact(iter: I) -> Self {
+        IterState {
+            exact: true,
+            ..Self::new(iter)
+        }
+    }
+
+    /// Pulls the next item out of the iterator, making sure a panic
+    /// never makes it past here
+    fn advance(&mut self) -> Result<I::Item, IterStatus> {
+        self.advance_with(Iterator::next)
+    }
+
+    /// Pulls an item out of the iterator with `next`, which is either
+    /// `Iterator::next` or `DoubleEndedIterator::next_back`
+    fn advance_with(&mut self, next: fn(&mut I) -> Option<I::Item>) -> Result<I::Item, IterStatus> {
+        // A poisoned iterator is never touched again
+        if let Some(message) = &self.panic {
+            return Err(fail(IterStatus::Panicked, message.as_str()));
+        }
+        if self.check_cancelled() {
+            return Err(IterStatus::Cancelled);
+        }
+        // An iterator that already finished stays finished
+        let iter = match &mut self.iter {
+            Some(iter) => iter,
+            None => return Err(IterStatus::AlreadyFinished),
+        };
+        match catch_unwind(AssertUnwindSafe(|| next(iter))) {
+            // The token may have been triggered while `next` was busy, in
+            // which case the item is thrown away
+            Ok(Some(x)) if self.check_cancelled() => {
+                let _ = catch_unwind(AssertUnwindSafe(|| drop(x)));
+                Err(IterStatus::Cancelled)
+            }
+            Ok(Some(x)) => Ok(x),
+            // A source that checks the token itself just stops
+            Ok(None) if self.check_cancelled() => Err(IterStatus::Cancelled),
+            Ok(None) => {
+                // Drop the iterator (and whatever it captured) right away,
+                // but keep the state around so `C#` can still call `destroy`
+                self.finish();
+                match self.panic {
+                    // `finish` already recorded the panic
+                    Some(_) => Err(IterStatus::Panicked),
+                    None => Err(IterStatus::Exhausted),
+                }
+            }
+            Err(payload) => {
+                self.poison(&*payload);
+                Err(IterStatus::Panicked)
+            }
+        }
+    }
+
+    /// Writes up to `len` items into `buf`, returning how many were
+    /// written along with what stopped it, which is `IterStatus::Item`
+    /// if the buffer filled up
+    unsafe fn advance_chunk(&mut self, buf: *mut I::Item, len: usize) -> (usize, IterStatus) {
+        if let Some(message) = &self.panic {
+            return (0, fail(IterStatus::Panicked, message.as_str()));
+        }
+        if self.check_cancelled() {
+            return (0, IterStatus::Cancelled);
+        }
+        let iter = match &mut self.iter {
+            Some(iter) => iter,
+            None => return (0, IterStatus::AlreadyFinished),
+        };
+        let cancel = self.cancel.as_ref();
+        let mut written = 0;
+        // Fill the buffer inside a single `catch_unwind` instead of
+        // going through `advance` for each item
+        let filled = catch_unwind(AssertUnwindSafe(|| {
+            while written < len {
+                match iter.next() {
+                    // Same as in `advance_with`, but items already
+                    // written stay valid, so a cancelled chunk is just a
+                    // short one
+                    Some(_) if cancel.is_some_and(CancellationToken::is_cancelled) => return false,
+                    Some(x) => std::ptr::write(buf.add(written), x),
+                    None => return false,
+                }
+                written += 1;
+            }
+            true
+        }));
+        let status = match filled {
+            Ok(true) => return (len, IterStatus::Item),
+            Ok(false) if self.check_cancelled() => return (written, IterStatus::Cancelled),
+            Ok(false) => IterStatus::Exhausted,
+            Err(payload) => {
+                self.poison(&*payload);
+                IterStatus::Panicked
             }
+        };
+        self.finish();
+        match self.panic {
+            Some(_) => (written, IterStatus::Panicked),
+            None => (written, status),
         }
     }
+
+    /// Asks the iterator how many items it has left, poisoning it if
+    /// that panics
+    fn size_hint(&mut self) -> FfiSizeHint {
+        let iter = match &self.iter {
+            Some(iter) => iter,
+            None => return FfiSizeHint::DONE,
+        };
+        match catch_unwind(AssertUnwindSafe(|| iter.size_hint())) {
+            Ok((lower, upper)) => FfiSizeHint {
+                lower,
+                upper: upper.unwrap_or(0),
+                has_upper: upper.is_some(),
+                exact: self.exact,
+            },
+            Err(payload) => {
+                self.poison(&*payload);
+                FfiSizeHint::DONE
+            }
+        }
+    }
+
+    /// Copies the panic or error message into `buf`, see `message_impl_ffi`
+    unsafe fn copy_message(&self, buf: *mut u8, len: usize) -> usize {
+        match self.panic.as_ref().or(self.error.as_ref()) {
+            Some(message) => copy_to_buffer(message, buf, len),
+            None => 0,
+        }
+    }
+
+    /// Drops the iterator once its token was triggered, returning whether
+    /// it was
+    fn check_cancelled(&mut self) -> bool {
+        if !self.cancelled && self.iter.is_some() && self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            self.cancelled = true;
+            self.finish();
+        }
+        self.cancelled
+    }
+
+    /// Marks the iterator as poisoned by a caught panic, and drops it
+    fn poison(&mut self, payload: &(dyn Any + Send)) {
+        let message = self.panic.get_or_insert_with(|| panic_message(payload));
+        fail(IterStatus::Panicked, message.as_str());
+        self.finish();
+    }
+
+    /// Drops the iterator, recording a panic from its destructor instead
+    /// of letting it through
+    fn finish(&mut self) {
+        if let Some(iter) = self.iter.take() {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| drop(iter))) {
+                let message = self.panic.get_or_insert_with(|| panic_message(&*payload));
+                fail(IterStatus::Panicked, message.as_str());
+            }
+        }
+    }
+}
+
+/// Copies `message` into `buf` as UTF-8, writing at most `len` bytes, and
+/// returns its full length. This is how every message gets to `C#`
+unsafe fn copy_to_buffer(message: &str, buf: *mut u8, len: usize) -> usize {
+    if !buf.is_null() {
+        std::ptr::copy_nonoverlapping(message.as_ptr(), buf, message.len().min(len));
+    }
+    message.len()
+}
+
+/// Gets the message out of a panic payload, which is almost always
+/// either a `&str` or a `String`
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// The "iterator" we pass to `C#`
+///
+/// `T` can be anything, including types without a sensible `Default`
+/// like `NonZeroU32`: the slot `C#` passes to `internal_iter` is only
+/// ever written to, never read or dropped, so it can hold anything
+/// (including garbage) beforehand.
+#[repr(C)]
+pub struct CSharpIteratorOut<T> {
+    /// The function we pass `C#`. It's called by `C#` and recieves the
+    /// pointer to the `Box`ed iterator. It's instantiated for the exact
+    /// iterator type behind `pointer`, so there's no dynamic dispatch
+    pub internal_iter: unsafe extern "C" fn(iter: *mut c_void, data: *mut T) -> IterStatus,
+    /// A thin pointer to the iterator's state that gets leaked. Only the
+    /// functions next to it know what type it really points to
+    pub pointer: *mut c_void,
+    /// The function `C#` calls once it's done with the iterator, whether
+    /// or not it was run to the end. Frees `pointer`, after which none
+    /// of these functions may be called with it again
+    pub destroy: unsafe extern "C" fn(iter: *mut c_void),
+    /// The function `C#` calls to get the panic message after
+    /// `internal_iter` returned `IterStatus::Panicked`, or the error
+    /// message after it returned `IterStatus::Error` for an error item
+    pub message: unsafe extern "C" fn(iter: *mut c_void, buf: *mut u8, len: usize) -> usize,
+    /// The function `C#` calls to hand an item back once it's done with
+    /// it. Every item written by `internal_iter` belongs to `C#` until
+    /// it's passed here, and this works even after `destroy`
+    pub release_item: unsafe extern "C" fn(item: *mut T),
+    /// The function `C#` calls to find out how many items are left
+    pub size_hint: unsafe extern "C" fn(iter: *mut c_void) -> FfiSizeHint,
+    /// The function `C#` calls to get many items at once, instead of
+    /// calling `internal_iter` for each of them
+    pub next_chunk: unsafe extern "C" fn(iter: *mut c_void, buf: *mut T, len: usize, status: *mut IterStatus) -> usize,
+}
+
+/// A stock function that handles iterator work.
+///
+/// `data` is treated as uninitialized: whatever was there before is
+/// overwritten without being dropped, and the item written there is
+/// owned by the caller until it's passed to `release_item_impl_ffi`.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form` and not have
+/// been passed to `destroy_impl_ffi` yet, and `data` must be null or
+/// valid for writes.
+pub unsafe extern "C" fn iter_impl_ffi<I: Iterator>(p: *mut c_void, data: *mut I::Item) -> IterStatus {
+    let p = p as *mut IterState<I>;
+    if p.is_null() {
+        return fail(IterStatus::InvalidHandle, NULL_ITERATOR);
+    }
+    // Don't pull an item out that we'd have nowhere to put
+    if data.is_null() {
+        return fail(IterStatus::Error, NULL_DATA);
+    }
+    match (*p).advance() {
+        // If there is new data...
+        Ok(x) => {
+            // Write it to the pointer we got, without dropping the
+            // garbage (or previous item) that's there...
+            std::ptr::write(data, x);
+            // And tell `C#` that it can poll again
+            IterStatus::Item
+        }
+        // Otherwise tell `C#` why there isn't
+        Err(status) => status,
+    }
+}
+
+/// A stock function that handles iterator work in bulk, so that `C#`
+/// only has to cross over once per `len` items.
+///
+/// Writes up to `len` items into `buf`, which is treated as
+/// uninitialized the same way `iter_impl_ffi` treats `data`, and returns
+/// how many were written. If `status` isn't null, it's set to
+/// `IterStatus::Item` when the buffer was filled, or to whatever stopped
+/// the iterator early otherwise. Items written before the iterator
+/// stopped are still valid, even if it panicked.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form` and not have
+/// been passed to `destroy_impl_ffi` yet, `buf` must be null or valid
+/// for `len` writes, and `status` must be null or valid for a write.
+pub unsafe extern "C" fn next_chunk_impl_ffi<I: Iterator>(p: *mut c_void, buf: *mut I::Item, len: usize, status: *mut IterStatus) -> usize {
+    let p = p as *mut IterState<I>;
+    let (written, result) = if p.is_null() {
+        (0, fail(IterStatus::InvalidHandle, NULL_ITERATOR))
+    } else if buf.is_null() && len != 0 {
+        (0, fail(IterStatus::Error, NULL_DATA))
+    } else {
+        (*p).advance_chunk(buf, len)
+    };
+    if !status.is_null() {
+        *status = result;
+    }
+    written
 }
 
-impl<T: Sized + Default + std::fmt::Debug> CSharpIteratorOut<T> {
-    /// Creates a `CSharpIteratorOut<T>` from an iterator over `T`
+/// A stock function that frees the iterator behind a `CSharpIteratorOut`.
+/// Works the same on an exhausted or poisoned iterator as on a
+/// half-consumed one, and does nothing when given a null pointer.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form`, and must not
+/// be used again after this call.
+pub unsafe extern "C" fn destroy_impl_ffi<I: Iterator>(p: *mut c_void) {
+    let p = p as *mut IterState<I>;
+    if !p.is_null() {
+        let mut state = Box::from_raw(p);
+        // Dropping the iterator is the only part that can panic
+        state.finish();
+    }
+}
+
+/// A stock function that copies the panic message of a poisoned iterator,
+/// or else the message of the error the last item was, into `buf` as
+/// UTF-8, writing at most `len` bytes. Returns the full length of the
+/// message in bytes, or 0 if there is none, so it can be called with a
+/// null `buf` first to size the buffer.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form` and not have
+/// been passed to `destroy_impl_ffi` yet, and `buf` must be null or valid
+/// for `len` bytes of writes.
+pub unsafe extern "C" fn message_impl_ffi<I: Iterator>(p: *mut c_void, buf: *mut u8, len: usize) -> usize {
+    let p = p as *mut IterState<I>;
+    if p.is_null() {
+        return 0;
+    }
+    (*p).copy_message(buf, len)
+}
+
+/// A stock function that drops an item that `iter_impl_ffi` handed out.
+/// Does nothing when given a null pointer, and never lets a panic from
+/// the item's destructor through.
+///
+/// # Safety
+/// `item` must be null or point to an item written by `iter_impl_ffi`
+/// that hasn't been released yet. The slot is uninitialized afterwards.
+pub unsafe extern "C" fn release_item_impl_ffi<T>(item: *mut T) {
+    if !item.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| std::ptr::drop_in_place(item)));
+    }
+}
+
+/// A stock function that tells `C#` how many items are left, according
+/// to the iterator's `size_hint`. An iterator that's finished or poisoned
+/// has exactly 0 items left.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpIteratorOut::form` and not have
+/// been passed to `destroy_impl_ffi` yet.
+pub unsafe extern "C" fn size_hint_impl_ffi<I: Iterator>(p: *mut c_void) -> FfiSizeHint {
+    let p = p as *mut IterState<I>;
+    if p.is_null() {
+        return FfiSizeHint::DONE;
+    }
+    (*p).size_hint()
+}
+
+impl<T> CSharpIteratorOut<T> {
+    /// Creates a `CSharpIteratorOut<T>` from an iterator over `T`. The
+    /// iterator is boxed as is, and the stock functions are instantiated
+    /// for `D` itself
     pub fn form<D: Iterator<Item=T> + 'static>(iter: D) -> Self {
+        Self::from_state(IterState::new(iter))
+    }
+
+    /// Creates a `CSharpIteratorOut<T>` from an iterator that knows
+    /// exactly how many items it has, so that `size_hint` can promise
+    /// `C#` an exact length
+    pub fn form_exact<D: ExactSizeIterator<Item=T> + 'static>(iter: D) -> Self {
+        Self::from_state(IterState::new_exact(iter))
+    }
+
+    /// Creates a `CSharpIteratorOut<T>` from an iterator over `T` that
+    /// gets type erased into a `Box<dyn Iterator<Item=T>>` first. This
+    /// costs an extra allocation and a virtual call per item, but all
+    /// iterators over `T` then share the same stock functions
+    pub fn form_boxed<D: Iterator<Item=T> + 'static>(iter: D) -> Self
+    where
+        T: 'static,
+    {
+        Self::form(Box::new(iter) as Box<dyn Iterator<Item=T>>)
+    }
+
+    fn from_state<D: Iterator<Item=T> + 'static>(state: IterState<D>) -> Self {
         CSharpIteratorOut {
             // Uses the stock function
-            internal_iter: iter_impl_ffi,
-            // Leaks the pointer so that it doesn't get dropped until 
-            // we get a None value in `iter_impl_ffi`
-            pointer: Box::into_raw(Box::new(Box::new(iter) as _))
+            internal_iter: iter_impl_ffi::<D>,
+            // Leaks the pointer so that it doesn't get dropped until
+            // `C#` calls `destroy`
+            pointer: Box::into_raw(Box::new(state)) as *mut c_void,
+            // Uses the stock destructor
+            destroy: destroy_impl_ffi::<D>,
+            // Uses the stock message getter
+            message: message_impl_ffi::<D>,
+            // Uses the stock item destructor
+            release_item: release_item_impl_ffi,
+            // Uses the stock size hint
+            size_hint: size_hint_impl_ffi::<D>,
+            // Uses the stock bulk function
+            next_chunk: next_chunk_impl_ffi::<D>,
         }
     }
 }
 
+impl<T: 'static> CSharpIteratorOut<FfiVec<T>> {
+    /// Creates a `CSharpIteratorOut<FfiVec<T>>` from an iterator over
+    /// anything that turns into a `Vec<T>`, which is how collections
+    /// should be streamed to `C#`
+    pub fn form_vecs<V: Into<Vec<T>>, D: Iterator<Item=V> + 'static>(iter: D) -> Self {
+        Self::form(iter.map(|v| FfiVec::from(v.into())))
+    }
+}
+
 /// An example function:
-/// 
-/// Creates an `Iterator<Item=Vec<usize>>` with each one counting up 
+///
+/// Creates an `Iterator<Item=FfiVec<usize>>` with each one counting up
 /// to the current iteration
+///
+/// # Safety
+/// `cs` must be null or valid for writes.
 #[no_mangle]
-pub extern fn get_iterator(cs: &mut CSharpIteratorOut<Vec<usize>>) {
-    let data = 0..40;
-    let iterator = CSharpIteratorOut::form(data.map(|x| {(0..x).collect::<Vec<usize>>()}));
-    *cs = iterator;
+pub unsafe extern "C" fn get_iterator(cs: *mut CSharpIteratorOut<FfiVec<usize>>) {
+    export_into(cs, || {
+        let data = 0..40;
+        CSharpIteratorOut::form_vecs(data.map(|x| {(0..x).collect::<Vec<usize>>()}))
+    });
+}
+
+/// An example function:
+///
+/// Creates an `Iterator<Item=FfiUtf16>` of lines, ready to be turned
+/// into `C#` strings
+///
+/// # Safety
+/// `cs` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn get_string_iterator(cs: *mut CSharpIteratorOut<FfiUtf16>) {
+    export_into(cs, || {
+        let data = 0..10;
+        CSharpIteratorOut::form_utf16(data.map(|x| format!("Line {}: {}", x, "ü".repeat(x))))
+    });
+}
+
+/// An example function:
+///
+/// Creates an endless `Iterator<Item=u64>` that takes a while per item,
+/// and stops once `token` is triggered
+///
+/// # Safety
+/// `cs` must be null or valid for writes, and `token` must be null or
+/// come from `cancellation_token_new` and not have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn get_slow_iterator(cs: *mut CSharpIteratorOut<u64>, token: *const CancellationToken) {
+    export_into(cs, || {
+        let slow = (0..).inspect(|_| std::thread::sleep(std::time::Duration::from_millis(100)));
+        match token.as_ref() {
+            Some(token) => CSharpIteratorOut::form_cancellable(slow, token),
+            None => CSharpIteratorOut::form(slow),
+        }
+    });
+}
+
+/// Counts up to `count`, waiting a while before each number without
+/// blocking anyone: a thread sleeps in the meantime, and wakes the stream
+/// up once it's done
+struct Ticks {
+    next: u64,
+    count: u64,
+    /// Set by the sleeping thread, while there is one
+    ready: Option<Arc<AtomicBool>>,
+}
+
+impl Stream for Ticks {
+    type Item = u64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u64>> {
+        if self.next == self.count {
+            return Poll::Ready(None);
+        }
+        match &self.ready {
+            Some(ready) if ready.load(Ordering::SeqCst) => {
+                self.ready = None;
+                self.next += 1;
+                Poll::Ready(Some(self.next - 1))
+            }
+            Some(_) => Poll::Pending,
+            None => {
+                let ready = Arc::new(AtomicBool::new(false));
+                let (done, waker) = (ready.clone(), cx.waker().clone());
+                std::thread::spawn(move || {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    done.store(true, Ordering::SeqCst);
+                    waker.wake();
+                });
+                self.ready = Some(ready);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// An example function:
+///
+/// Creates a `Stream<Item=u64>` counting up to `count`, with an awaited
+/// pause before each number
+///
+/// # Safety
+/// `cs` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn get_ticking_stream(cs: *mut CSharpStreamOut<u64>, count: u64) {
+    export_into(cs, || CSharpStreamOut::form(Ticks { next: 0, count, ready: None }));
+}
+
+/// An example function:
+///
+/// Counts from `start` up to `end` in steps of `step`, written with
+/// `#[export_iterator]` instead of by hand. A `step` of 0 panics, which
+/// `C#` finds out about through the last error
+#[export_iterator]
+pub fn get_range_iterator(start: u64, end: u64, step: u64) -> impl Iterator<Item=u64> {
+    (start..end).step_by(step as usize)
+}
+
+/// An example function:
+///
+/// Splits a string from `C#` into words, ready to be turned back into
+/// `C#` strings
+#[export_iterator]
+pub fn get_word_iterator(text: String) -> impl Iterator<Item=FfiUtf16> {
+    let words: Vec<FfiUtf16> = text.split_whitespace().map(FfiUtf16::from).collect();
+    words.into_iter()
+}
+
+/// An example function:
+///
+/// Adds up a sequence of numbers that `C#` owns, wrapping on overflow
+#[no_mangle]
+pub extern "C" fn sum_foreign(iter: ForeignIterator<u64>) -> u64 {
+    iter.fold(0, u64::wrapping_add)
+}
+
+/// An example function:
+///
+/// Pushes the squares of `0..count` into a `C#` callback, stopping
+/// whenever it says so
+#[no_mangle]
+pub extern "C" fn push_squares(count: u64, sink: CSharpSink<u64>) -> IterStatus {
+    drive_into_callback((0..count).map(|x| x.wrapping_mul(x)), &sink)
+}
+
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::mem::MaybeUninit;
+    use std::num::NonZeroU32;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::common::{destroy, last_error, message, next, next_chunk};
+
+    /// An iterator over `0..left` that panics wherever it's told to
+    #[derive(Default)]
+    struct Bomb {
+        left: u32,
+        in_next: bool,
+        in_drop: bool,
+        in_size_hint: bool,
+    }
+
+    impl Iterator for Bomb {
+        type Item = u32;
+
+        fn next(&mut self) -> Option<u32> {
+            assert!(!self.in_next, "next");
+            self.left = self.left.checked_sub(1)?;
+            Some(self.left)
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            assert!(!self.in_size_hint, "size_hint");
+            (self.left as usize, Some(self.left as usize))
+        }
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            // Not while already unwinding, which would abort
+            if self.in_drop && !std::thread::panicking() {
+                panic!("drop");
+            }
+        }
+    }
+
+    /// Whether the iterator behind `cs` was dropped
+    fn dropped(cs: &CSharpIteratorOut<u32>) -> bool {
+        unsafe { (*(cs.pointer as *const IterState<Bomb>)).iter.is_none() }
+    }
+
+    #[test]
+    fn next_panic_poisons() {
+        let cs = CSharpIteratorOut::form(Bomb { left: 3, in_next: true, ..Bomb::default() });
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        assert!(dropped(&cs));
+        assert_eq!(message(&cs), "next");
+        assert_eq!(last_error(), (IterStatus::Panicked, "next".to_string()));
+        assert_eq!(unsafe { (cs.size_hint)(cs.pointer) }, FfiSizeHint::DONE);
+        destroy(&cs);
+    }
+
+    /// Dropping happens when the iterator runs out, so that's where its
+    /// panic shows up
+    #[test]
+    fn drop_panic_poisons() {
+        let cs = CSharpIteratorOut::form(Bomb { left: 1, in_drop: true, ..Bomb::default() });
+        assert_eq!(next(&cs), (Some(0), IterStatus::Item));
+        assert_eq!(message(&cs), "");
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        assert!(dropped(&cs));
+        assert_eq!(message(&cs), "drop");
+        assert_eq!(last_error(), (IterStatus::Panicked, "drop".to_string()));
+        destroy(&cs);
+    }
+
+    /// Or in `destroy`, if it never ran out
+    #[test]
+    fn drop_panic_in_destroy() {
+        let cs = CSharpIteratorOut::form(Bomb { left: 5, in_drop: true, ..Bomb::default() });
+        destroy(&cs);
+        assert_eq!(last_error(), (IterStatus::Panicked, "drop".to_string()));
+    }
+
+    #[test]
+    fn size_hint_panic_poisons() {
+        let cs = CSharpIteratorOut::form(Bomb { left: 3, in_size_hint: true, ..Bomb::default() });
+        assert_eq!(unsafe { (cs.size_hint)(cs.pointer) }, FfiSizeHint::DONE);
+        assert!(dropped(&cs));
+        assert_eq!(message(&cs), "size_hint");
+        assert_eq!(last_error(), (IterStatus::Panicked, "size_hint".to_string()));
+        // The iterator is never asked for anything again
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        destroy(&cs);
+    }
+
+    /// Counts how many times it was dropped
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    /// Items are `C#`'s until it hands them back, even after `destroy`
+    #[test]
+    fn release_item_drops_once() {
+        let drops = Rc::new(Cell::new(0));
+        let items: Vec<_> = (0..3).map(|_| Counted(drops.clone())).collect();
+        let cs = CSharpIteratorOut::form(items.into_iter());
+        let mut slots = [MaybeUninit::uninit(), MaybeUninit::uninit()];
+        for slot in &mut slots {
+            assert_eq!(unsafe { (cs.internal_iter)(cs.pointer, slot.as_mut_ptr()) }, IterStatus::Item);
+        }
+        assert_eq!(drops.get(), 0);
+
+        unsafe { (cs.release_item)(slots[0].as_mut_ptr()) };
+        assert_eq!(drops.get(), 1);
+        // Only the item that was never handed out
+        destroy(&cs);
+        assert_eq!(drops.get(), 2);
+        unsafe { (cs.release_item)(slots[1].as_mut_ptr()) };
+        assert_eq!(drops.get(), 3);
+        unsafe { (cs.release_item)(std::ptr::null_mut()) };
+        assert_eq!(drops.get(), 3);
+    }
+
+    /// Items without a `Default` are written into a slot that was never
+    /// initialized
+    #[test]
+    fn non_zero_items() {
+        let cs = CSharpIteratorOut::form((1..4).filter_map(NonZeroU32::new));
+        let mut slot = MaybeUninit::<NonZeroU32>::uninit();
+        for x in 1..4 {
+            assert_eq!(unsafe { (cs.internal_iter)(cs.pointer, slot.as_mut_ptr()) }, IterStatus::Item);
+            assert_eq!(unsafe { slot.assume_init() }.get(), x);
+        }
+        assert_eq!(unsafe { (cs.internal_iter)(cs.pointer, slot.as_mut_ptr()) }, IterStatus::Exhausted);
+        destroy(&cs);
+    }
+
+    fn size_hint<T>(cs: &CSharpIteratorOut<T>) -> FfiSizeHint {
+        unsafe { (cs.size_hint)(cs.pointer) }
+    }
+
+    #[test]
+    fn size_hint_exactness() {
+        let cs = CSharpIteratorOut::form_exact(0..3u32);
+        assert_eq!(size_hint(&cs), FfiSizeHint { lower: 3, upper: 3, has_upper: true, exact: true });
+        next(&cs);
+        assert_eq!(size_hint(&cs), FfiSizeHint { lower: 2, upper: 2, has_upper: true, exact: true });
+        destroy(&cs);
+
+        // The same bounds, but only `form_exact` promises them
+        let cs = CSharpIteratorOut::form(0..3u32);
+        assert_eq!(size_hint(&cs), FfiSizeHint { lower: 3, upper: 3, has_upper: true, exact: false });
+        destroy(&cs);
+
+        let cs = CSharpIteratorOut::form((0..).filter(|x: &u32| *x != 1));
+        assert_eq!(size_hint(&cs), FfiSizeHint { lower: 0, upper: 0, has_upper: false, exact: false });
+        destroy(&cs);
+    }
+
+    #[test]
+    fn size_hint_when_done() {
+        let cs = CSharpIteratorOut::form((0..3u32).filter(|_| true));
+        assert_eq!(next_chunk(&cs, 5).1, IterStatus::Exhausted);
+        assert_eq!(size_hint(&cs), FfiSizeHint::DONE);
+        destroy(&cs);
+    }
+
+    /// Every status an iterator can end with, after which it says the
+    /// same thing forever, whichever of `internal_iter` and `next_chunk`
+    /// is asked. Only `Exhausted` turns into `AlreadyFinished`, so `C#`
+    /// sees it exactly once
+    fn assert_sticky<T: PartialEq + std::fmt::Debug>(cs: &CSharpIteratorOut<T>, status: IterStatus) {
+        let after = match status {
+            IterStatus::Exhausted => IterStatus::AlreadyFinished,
+            status => status,
+        };
+        for _ in 0..2 {
+            assert_eq!(next(cs), (None, after));
+            assert_eq!(next_chunk(cs, 4), (vec![], after));
+        }
+    }
+
+    #[test]
+    fn exhausted_once() {
+        let cs = CSharpIteratorOut::form(0..2u32);
+        assert_eq!(next(&cs), (Some(0), IterStatus::Item));
+        assert_eq!(next(&cs), (Some(1), IterStatus::Item));
+        assert_eq!(next(&cs), (None, IterStatus::Exhausted));
+        assert_sticky(&cs, IterStatus::Exhausted);
+        destroy(&cs);
+
+        let cs = CSharpIteratorOut::form(0..2u32);
+        assert_eq!(next_chunk(&cs, 2), (vec![0, 1], IterStatus::Item));
+        assert_eq!(next_chunk(&cs, 2), (vec![], IterStatus::Exhausted));
+        assert_sticky(&cs, IterStatus::Exhausted);
+        destroy(&cs);
+    }
+
+    #[test]
+    fn panicked_is_sticky() {
+        let cs = CSharpIteratorOut::form(Bomb { left: 3, in_next: true, ..Bomb::default() });
+        assert_eq!(next(&cs), (None, IterStatus::Panicked));
+        assert_sticky(&cs, IterStatus::Panicked);
+        assert_eq!(last_error(), (IterStatus::Panicked, "next".to_string()));
+        destroy(&cs);
+
+        let cs = CSharpIteratorOut::form(Bomb { left: 3, in_next: true, ..Bomb::default() });
+        assert_eq!(next_chunk(&cs, 4), (vec![], IterStatus::Panicked));
+        assert_sticky(&cs, IterStatus::Panicked);
+        assert_eq!(last_error(), (IterStatus::Panicked, "next".to_string()));
+        destroy(&cs);
+    }
+
+    #[test]
+    fn cancelled_is_sticky() {
+        let token = CancellationToken::new();
+        let cs = CSharpIteratorOut::form_cancellable(0..u32::MAX, &token);
+        assert_eq!(next(&cs), (Some(0), IterStatus::Item));
+        token.cancel();
+        assert_eq!(next(&cs), (None, IterStatus::Cancelled));
+        assert_sticky(&cs, IterStatus::Cancelled);
+        destroy(&cs);
+
+        let token = CancellationToken::new();
+        let cs = CSharpIteratorOut::form_cancellable(0..u32::MAX, &token);
+        assert_eq!(next_chunk(&cs, 2), (vec![0, 1], IterStatus::Item));
+        token.cancel();
+        assert_eq!(next_chunk(&cs, 2), (vec![], IterStatus::Cancelled));
+        assert_sticky(&cs, IterStatus::Cancelled);
+        destroy(&cs);
+    }
+
+    #[test]
+    fn chunk_panic_is_recorded() {
+        let cs = CSharpIteratorOut::form((0..10u32).inspect(|&x| assert!(x < 3, "boom {}", x)));
+        assert_eq!(next_chunk(&cs, 8), (vec![0, 1, 2], IterStatus::Panicked));
+        assert_eq!(last_error(), (IterStatus::Panicked, "boom 3".to_string()));
+        destroy(&cs);
+    }
 }
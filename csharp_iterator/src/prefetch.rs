@@ -0,0 +1,92 @@
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+use crate::CSharpIteratorOut;
+
+/// What the producer thread sends: an item, or the panic that ended it
+type Produced<T> = Result<T, Box<dyn Any + Send>>;
+
+/// An iterator that reads the items another thread produces
+struct Prefetch<T> {
+    items: Receiver<Produced<T>>,
+    /// Tells the producer to stop before its next item
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T> Iterator for Prefetch<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self.items.recv() {
+            Ok(Ok(x)) => Some(x),
+            // Panic again on this side, so it poisons the iterator the
+            // same way a panic in `next` would
+            Ok(Err(payload)) => resume_unwind(payload),
+            // The producer ran out
+            Err(_) => None,
+        }
+    }
+}
+
+impl<T> Drop for Prefetch<T> {
+    fn drop(&mut self) {
+        // The receiver goes right after this, which gets the producer
+        // out of a `send` it's blocked on
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Runs `iter` to the end (or until cancelled), sending every item
+fn produce<D: Iterator>(mut iter: D, tx: SyncSender<Produced<D::Item>>, cancelled: &AtomicBool) {
+    while !cancelled.load(Ordering::SeqCst) {
+        match catch_unwind(AssertUnwindSafe(|| iter.next())) {
+            Ok(Some(x)) => {
+                // Nobody's listening anymore
+                if tx.send(Ok(x)).is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(payload) => {
+                let _ = tx.send(Err(payload));
+                return;
+            }
+        }
+    }
+    // A panic while dropping the iterator is sent along too, the same
+    // way `IterState::finish` records it
+    if let Err(payload) = catch_unwind(AssertUnwindSafe(|| drop(iter))) {
+        let _ = tx.send(Err(payload));
+    }
+}
+
+impl<T: Send + 'static> CSharpIteratorOut<T> {
+    /// Creates a `CSharpIteratorOut<T>` that runs `iter` on a thread of
+    /// its own, which stays up to `capacity` items ahead of `C#`. This
+    /// way slow items are produced while `C#` is still busy with the
+    /// last ones.
+    ///
+    /// With a `capacity` of 0 the channel is a rendezvous: the producer
+    /// still works on the next item while `C#` is busy, but can't get
+    /// any further ahead than that one.
+    ///
+    /// A panic on the producer thread poisons the iterator once `C#`
+    /// gets to it, with the same message. Destroying the iterator
+    /// cancels the producer without waiting for it, so it may finish
+    /// the item it's working on after `destroy` returned. `iter` is
+    /// always dropped on the producer thread.
+    pub fn form_prefetching<D: Iterator<Item=T> + Send + 'static>(iter: D, capacity: usize) -> Self {
+        let (tx, items) = sync_channel(capacity);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let producer = cancelled.clone();
+        thread::Builder::new()
+            .name("cs_iter prefetch".to_string())
+            .spawn(move || produce(iter, tx, &producer))
+            .expect("failed to spawn the prefetch thread");
+        Self::form(Prefetch { items, cancelled })
+    }
+}
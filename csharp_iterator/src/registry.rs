@@ -0,0 +1,357 @@
+use std::any::Any;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::last_error::{clear_last_error, fail, INVALID_HANDLE, NULL_DATA};
+use crate::{release_item_impl_ffi, FfiSizeHint, IterState, IterStatus};
+
+/// The state behind a registered iterator. Registered iterators can be
+/// reached from any thread, so they have to be `Send`
+type SendState<I> = Mutex<IterState<I>>;
+
+/// The "iterator" we pass to `C#` when it should be handed an opaque
+/// handle instead of a pointer.
+///
+/// The iterator lives in a global registry, and the handle is checked
+/// against it on every call, so a handle that was already destroyed, or
+/// never existed, gets `IterStatus::InvalidHandle` back instead of
+/// touching freed memory. It has the same fields as `CSharpIteratorOut`,
+/// with `handle` in place of `pointer`.
+#[repr(C)]
+pub struct CSharpIteratorHandle<T> {
+    /// The function we pass `C#`, which looks the handle up before
+    /// calling `next` on the iterator behind it
+    pub internal_iter: unsafe extern "C" fn(u64, *mut T) -> IterStatus,
+    /// The handle itself: the slot's index in the low 32 bits, and the
+    /// slot's generation in the high 32 bits
+    pub handle: u64,
+    /// The function `C#` calls once it's done with the iterator. Clears
+    /// the last error the first time, and records
+    /// `IterStatus::InvalidHandle` as the last error after that
+    pub destroy: extern "C" fn(u64),
+    /// The function `C#` calls to get the panic message after
+    /// `internal_iter` returned `IterStatus::Panicked`
+    pub message: unsafe extern "C" fn(u64, *mut u8, usize) -> usize,
+    /// The function `C#` calls to hand an item back once it's done with it
+    pub release_item: unsafe extern "C" fn(*mut T),
+    /// The function `C#` calls to find out how many items are left
+    pub size_hint: extern "C" fn(u64) -> FfiSizeHint,
+    /// The function `C#` calls to get many items at once
+    pub next_chunk: unsafe extern "C" fn(u64, *mut T, usize, *mut IterStatus) -> usize,
+}
+
+/// One place in the registry
+struct Slot {
+    /// Bumped every time the slot is emptied, so old handles stop matching
+    generation: u32,
+    /// The `SendState<I>` living here, if any
+    entry: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+/// Every registered iterator, regardless of item type
+struct Registry {
+    slots: Vec<Slot>,
+    /// Indices of empty slots, to be reused before growing `slots`
+    free: Vec<u32>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    slots: Vec::new(),
+    free: Vec::new(),
+});
+
+/// Locks a mutex, ignoring poisoning. Nothing panics while holding one
+/// of ours, since iterator panics are caught inside `IterState::advance`
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Registry {
+    fn insert(&mut self, entry: Arc<dyn Any + Send + Sync>) -> u64 {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                // Generations start at 1 so that 0 is never a valid handle
+                self.slots.push(Slot { generation: 1, entry: None });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.entry = Some(entry);
+        ((slot.generation as u64) << 32) | index as u64
+    }
+
+    /// Finds the slot a handle points to, if the handle is still current
+    fn slot(&mut self, handle: u64) -> Option<&mut Slot> {
+        let index = (handle & 0xFFFF_FFFF) as usize;
+        let generation = (handle >> 32) as u32;
+        self.slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == generation && slot.entry.is_some())
+    }
+
+    fn get(&mut self, handle: u64) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.slot(handle).and_then(|slot| slot.entry.clone())
+    }
+
+    fn remove(&mut self, handle: u64) -> Option<Arc<dyn Any + Send + Sync>> {
+        let slot = self.slot(handle)?;
+        let entry = slot.entry.take();
+        slot.generation = match slot.generation.wrapping_add(1) {
+            0 => 1,
+            generation => generation,
+        };
+        self.free.push((handle & 0xFFFF_FFFF) as u32);
+        entry
+    }
+}
+
+/// Looks a handle up, checking that it's current and that it really is
+/// an `I`. The registry is only locked for the lookup, so iterators can
+/// use other registered iterators
+fn lookup<I: Iterator + Send + 'static>(handle: u64) -> Option<Arc<SendState<I>>> {
+    let entry = lock(&REGISTRY).get(handle)?;
+    entry.downcast().ok()
+}
+
+/// A stock function that handles iterator work for registered iterators.
+///
+/// # Safety
+/// `data` must be null or valid for writes, and is treated the same as
+/// in `iter_impl_ffi`.
+pub unsafe extern "C" fn registered_iter_impl_ffi<I: Iterator + Send + 'static>(handle: u64, data: *mut I::Item) -> IterStatus {
+    let state = match lookup::<I>(handle) {
+        Some(state) => state,
+        None => return fail(IterStatus::InvalidHandle, INVALID_HANDLE),
+    };
+    // Don't pull an item out that we'd have nowhere to put
+    if data.is_null() {
+        return fail(IterStatus::Error, NULL_DATA);
+    }
+    let result = lock(&state).advance();
+    match result {
+        Ok(x) => {
+            std::ptr::write(data, x);
+            IterStatus::Item
+        }
+        Err(status) => status,
+    }
+}
+
+/// A stock function that handles iterator work in bulk for registered
+/// iterators, the same way as `next_chunk_impl_ffi`. The handle is only
+/// looked up once per chunk.
+///
+/// # Safety
+/// `buf` must be null or valid for `len` writes, and `status` must be
+/// null or valid for a write.
+pub unsafe extern "C" fn registered_next_chunk_impl_ffi<I: Iterator + Send + 'static>(handle: u64, buf: *mut I::Item, len: usize, status: *mut IterStatus) -> usize {
+    let (written, result) = match lookup::<I>(handle) {
+        None => (0, fail(IterStatus::InvalidHandle, INVALID_HANDLE)),
+        Some(_) if buf.is_null() && len != 0 => (0, fail(IterStatus::Error, NULL_DATA)),
+        Some(state) => lock(&state).advance_chunk(buf, len),
+    };
+    if !status.is_null() {
+        *status = result;
+    }
+    written
+}
+
+/// A stock function that removes a registered iterator and frees it.
+///
+/// Like `destroy_impl_ffi` it doesn't return anything. A handle that
+/// isn't current is recorded as `IterStatus::InvalidHandle` in the last
+/// error, which is cleared otherwise, so `C#` can tell the two apart.
+pub extern "C" fn registered_destroy_impl_ffi<I: Iterator + Send + 'static>(handle: u64) {
+    // Check the type before taking it out, so a forged handle to an
+    // iterator of another type can't destroy it
+    if lookup::<I>(handle).is_none() {
+        fail(IterStatus::InvalidHandle, INVALID_HANDLE);
+        return;
+    }
+    let entry = match lock(&REGISTRY).remove(handle) {
+        Some(entry) => entry,
+        // Someone else destroyed it in the meantime
+        None => {
+            fail(IterStatus::InvalidHandle, INVALID_HANDLE);
+            return;
+        }
+    };
+    // Before dropping, which records a panic of its own
+    clear_last_error();
+    if let Ok(state) = entry.downcast::<SendState<I>>() {
+        // Drop the iterator now, even if another thread still holds on
+        // to the state for a moment
+        lock(&state).finish();
+    }
+}
+
+/// A stock function that copies the panic message of a registered
+/// iterator, the same way as `message_impl_ffi`. Returns 0 for an
+/// invalid handle.
+///
+/// # Safety
+/// `buf` must be null or valid for `len` bytes of writes.
+pub unsafe extern "C" fn registered_message_impl_ffi<I: Iterator + Send + 'static>(handle: u64, buf: *mut u8, len: usize) -> usize {
+    match lookup::<I>(handle) {
+        Some(state) => lock(&state).copy_message(buf, len),
+        None => 0,
+    }
+}
+
+/// A stock function that tells `C#` how many items a registered iterator
+/// has left, the same way as `size_hint_impl_ffi`. An invalid handle has
+/// 0 items left.
+pub extern "C" fn registered_size_hint_impl_ffi<I: Iterator + Send + 'static>(handle: u64) -> FfiSizeHint {
+    match lookup::<I>(handle) {
+        Some(state) => lock(&state).size_hint(),
+        None => FfiSizeHint::DONE,
+    }
+}
+
+impl<T: 'static> CSharpIteratorHandle<T> {
+    /// Creates a `CSharpIteratorHandle<T>` from an iterator over `T`,
+    /// registering it until `C#` calls `destroy`
+    pub fn form<D: Iterator<Item=T> + Send + 'static>(iter: D) -> Self {
+        Self::register(IterState::new(iter))
+    }
+
+    /// Creates a `CSharpIteratorHandle<T>` from an iterator that knows
+    /// exactly how many items it has, like `CSharpIteratorOut::form_exact`
+    pub fn form_exact<D: ExactSizeIterator<Item=T> + Send + 'static>(iter: D) -> Self {
+        Self::register(IterState::new_exact(iter))
+    }
+
+    fn register<D: Iterator<Item=T> + Send + 'static>(state: IterState<D>) -> Self {
+        let state: SendState<D> = Mutex::new(state);
+        CSharpIteratorHandle {
+            internal_iter: registered_iter_impl_ffi::<D>,
+            handle: lock(&REGISTRY).insert(Arc::new(state)),
+            destroy: registered_destroy_impl_ffi::<D>,
+            message: registered_message_impl_ffi::<D>,
+            release_item: release_item_impl_ffi,
+            size_hint: registered_size_hint_impl_ffi::<D>,
+            next_chunk: registered_next_chunk_impl_ffi::<D>,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null_mut;
+
+    use super::*;
+    use crate::last_error_code;
+
+    /// Asks for the next item behind `handle` as if it held a `D`
+    fn next<D: Iterator + Send + 'static>(handle: u64) -> IterStatus {
+        let mut item = std::mem::MaybeUninit::<D::Item>::uninit();
+        unsafe { registered_iter_impl_ffi::<D>(handle, item.as_mut_ptr()) }
+    }
+
+    fn chunk<D: Iterator + Send + 'static>(handle: u64) -> IterStatus {
+        let mut status = IterStatus::Item;
+        unsafe { registered_next_chunk_impl_ffi::<D>(handle, null_mut(), 0, &mut status) };
+        status
+    }
+
+    #[test]
+    fn generation_is_bumped_on_remove() {
+        let mut registry = Registry { slots: Vec::new(), free: Vec::new() };
+        let first = registry.insert(Arc::new(()));
+        assert!(registry.remove(first).is_some());
+        assert!(registry.get(first).is_none());
+        assert!(registry.remove(first).is_none());
+
+        // The slot is reused under a new generation
+        let second = registry.insert(Arc::new(()));
+        assert_eq!(second & 0xFFFF_FFFF, first & 0xFFFF_FFFF);
+        assert_eq!(second >> 32, (first >> 32) + 1);
+        assert_eq!(registry.slots.len(), 1);
+        assert!(registry.get(first).is_none());
+        assert!(registry.get(second).is_some());
+    }
+
+    /// Wrapping around skips 0, so a zeroed handle never becomes valid
+    #[test]
+    fn generation_skips_zero() {
+        let mut registry = Registry { slots: Vec::new(), free: Vec::new() };
+        let first = registry.insert(Arc::new(()));
+        registry.slots[0].generation = u32::MAX;
+        assert!(registry.remove((u32::MAX as u64) << 32 | first & 0xFFFF_FFFF).is_some());
+        assert_eq!(registry.slots[0].generation, 1);
+    }
+
+    #[test]
+    fn zero_is_invalid() {
+        type D = std::ops::Range<u32>;
+        assert_eq!(next::<D>(0), IterStatus::InvalidHandle);
+        assert_eq!(chunk::<D>(0), IterStatus::InvalidHandle);
+        assert_eq!(registered_size_hint_impl_ffi::<D>(0).lower, 0);
+        registered_destroy_impl_ffi::<D>(0);
+        assert_eq!(last_error_code(), IterStatus::InvalidHandle);
+    }
+
+    #[test]
+    fn destroyed_handle_is_invalid() {
+        let cs = CSharpIteratorHandle::form(0..3u32);
+        assert_eq!(next::<std::ops::Range<u32>>(cs.handle), IterStatus::Item);
+        (cs.destroy)(cs.handle);
+        assert_eq!(last_error_code(), IterStatus::Item);
+
+        assert_eq!(unsafe { (cs.internal_iter)(cs.handle, &mut 0) }, IterStatus::InvalidHandle);
+        assert_eq!(chunk::<std::ops::Range<u32>>(cs.handle), IterStatus::InvalidHandle);
+        (cs.destroy)(cs.handle);
+        assert_eq!(last_error_code(), IterStatus::InvalidHandle);
+
+        // A new iterator in the same slot doesn't bring the old handle back
+        let again = CSharpIteratorHandle::form(0..3u32);
+        assert_eq!(unsafe { (cs.internal_iter)(cs.handle, &mut 0) }, IterStatus::InvalidHandle);
+        (again.destroy)(again.handle);
+    }
+
+    /// A handle used with the functions of another iterator type is
+    /// rejected, and left alone
+    #[test]
+    fn wrong_type_is_invalid() {
+        let cs = CSharpIteratorHandle::form(0..3u32);
+        type Other = std::vec::IntoIter<u32>;
+        assert_eq!(next::<Other>(cs.handle), IterStatus::InvalidHandle);
+        assert_eq!(chunk::<Other>(cs.handle), IterStatus::InvalidHandle);
+        assert_eq!(unsafe { registered_message_impl_ffi::<Other>(cs.handle, null_mut(), 0) }, 0);
+        registered_destroy_impl_ffi::<Other>(cs.handle);
+        assert_eq!(last_error_code(), IterStatus::InvalidHandle);
+
+        let mut item = 0;
+        assert_eq!(unsafe { (cs.internal_iter)(cs.handle, &mut item) }, IterStatus::Item);
+        assert_eq!(item, 0);
+        (cs.destroy)(cs.handle);
+        assert_eq!(last_error_code(), IterStatus::Item);
+    }
+
+    #[test]
+    fn size_hint() {
+        let cs = CSharpIteratorHandle::form_exact(0..3u32);
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint { lower: 3, upper: 3, has_upper: true, exact: true });
+        assert_eq!(next::<std::ops::Range<u32>>(cs.handle), IterStatus::Item);
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint { lower: 2, upper: 2, has_upper: true, exact: true });
+        (cs.destroy)(cs.handle);
+        // Destroyed handles are as done as can be
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint::DONE);
+
+        let cs = CSharpIteratorHandle::form(0..3u32);
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint { lower: 3, upper: 3, has_upper: true, exact: false });
+        assert_eq!(chunk::<std::ops::Range<u32>>(cs.handle), IterStatus::Item);
+        (cs.destroy)(cs.handle);
+
+        let cs = CSharpIteratorHandle::form((0..).filter(|x: &u32| *x != 1));
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint { lower: 0, upper: 0, has_upper: false, exact: false });
+        (cs.destroy)(cs.handle);
+
+        let cs = CSharpIteratorHandle::form(vec![1u32].into_iter());
+        let (mut buf, mut status) = ([0; 2], IterStatus::Item);
+        unsafe { (cs.next_chunk)(cs.handle, buf.as_mut_ptr(), 2, &mut status) };
+        assert_eq!(status, IterStatus::Exhausted);
+        assert_eq!((cs.size_hint)(cs.handle), FfiSizeHint::DONE);
+        (cs.destroy)(cs.handle);
+    }
+}
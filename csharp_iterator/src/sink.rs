@@ -0,0 +1,85 @@
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::last_error::fail;
+use crate::{panic_message, CSharpIteratorOut, IterStatus};
+
+/// A callback `C#` gives rust to push items into, for when rust should
+/// drive the loop instead of `C#` calling `internal_iter` over and over.
+///
+/// Each item is only lent to `push` for the duration of the call, and
+/// dropped by rust afterwards, so `C#` copies out what it needs and never
+/// has to release anything.
+#[repr(C)]
+pub struct CSharpSink<T> {
+    /// Passed back to `push`, see [Callbacks](crate#callbacks)
+    pub(crate) context: *mut c_void,
+    /// Takes the next item, returning false to stop early
+    pub(crate) push: unsafe extern "C" fn(*mut c_void, *const T) -> bool,
+}
+
+impl<T> CSharpSink<T> {
+    /// See [Callbacks](crate#callbacks).
+    ///
+    /// # Safety
+    /// `push` must be safe to call with `context` and any `*const T` for
+    /// as long as the sink is used.
+    pub unsafe fn new(context: *mut c_void, push: unsafe extern "C" fn(*mut c_void, *const T) -> bool) -> Self {
+        CSharpSink { context, push }
+    }
+
+    /// Lends one item to `C#`, returning whether it wants more
+    fn push(&self, item: &T) -> bool {
+        unsafe { (self.push)(self.context, item) }
+    }
+}
+
+/// Pushes every item of `iter` into `sink` until either runs out.
+///
+/// Returns `IterStatus::Exhausted` if every item was pushed,
+/// `IterStatus::Item` if the sink stopped early (there may be more items),
+/// and `IterStatus::Panicked` if the iterator panicked, in which case the
+/// panic message is the last error.
+pub fn drive_into_callback<I: IntoIterator>(iter: I, sink: &CSharpSink<I::Item>) -> IterStatus {
+    let driven = catch_unwind(AssertUnwindSafe(|| {
+        for item in iter {
+            if !sink.push(&item) {
+                return IterStatus::Item;
+            }
+        }
+        IterStatus::Exhausted
+    }));
+    match driven {
+        Ok(status) => status,
+        Err(payload) => fail(IterStatus::Panicked, panic_message(&*payload)),
+    }
+}
+
+impl<T> CSharpIteratorOut<T> {
+    /// Pushes every item of this iterator into `sink`, going through its
+    /// function pointers the same way `C#` would, and destroys it
+    /// afterwards. Returns the same as `drive_into_callback`, or whatever
+    /// else `internal_iter` returned that stopped it.
+    ///
+    /// # Safety
+    /// `self` must be a live iterator from one of the `form` functions,
+    /// which can't be used again after this.
+    pub unsafe fn drive_into_callback(self, sink: &CSharpSink<T>) -> IterStatus {
+        let mut slot = MaybeUninit::<T>::uninit();
+        let status = loop {
+            match (self.internal_iter)(self.pointer, slot.as_mut_ptr()) {
+                IterStatus::Item => {
+                    let more = sink.push(&*slot.as_ptr());
+                    (self.release_item)(slot.as_mut_ptr());
+                    if !more {
+                        break IterStatus::Item;
+                    }
+                }
+                status => break status,
+            }
+        };
+        (self.destroy)(self.pointer);
+        status
+    }
+}
@@ -0,0 +1,421 @@
+use std::cell::UnsafeCell;
+use std::ffi::c_void;
+use std::future::Future;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use futures_core::Stream;
+
+use crate::last_error::{fail, NULL_DATA, NULL_ITERATOR, REQUEST_PENDING};
+use crate::registry::lock;
+use crate::{copy_to_buffer, panic_message, release_item_impl_ffi, IterStatus};
+
+/// The callback `C#` passes along with each request for an item, called
+/// exactly once with how the request went. This is what `C#` completes
+/// its `TaskCompletionSource` from.
+///
+/// It may be called before `request_next` even returns, if the item was
+/// already there, or later from whichever thread woke the stream up.
+#[repr(C)]
+pub struct CSharpCompletion {
+    /// Passed back to `complete`, see [Callbacks](crate#callbacks)
+    pub(crate) context: *mut c_void,
+    /// Takes the status of the request: `IterStatus::Item` if the slot
+    /// now holds an item, or whatever else stopped the stream
+    pub(crate) complete: unsafe extern "C" fn(*mut c_void, IterStatus),
+}
+
+// `C#` promises `complete` can be called from any thread
+unsafe impl Send for CSharpCompletion {}
+
+impl CSharpCompletion {
+    /// See [Callbacks](crate#callbacks).
+    ///
+    /// # Safety
+    /// `complete` must be safe to call once with `context`, from any
+    /// thread.
+    pub unsafe fn new(context: *mut c_void, complete: unsafe extern "C" fn(*mut c_void, IterStatus)) -> Self {
+        CSharpCompletion { context, complete }
+    }
+
+    fn complete(self, status: IterStatus) {
+        unsafe { (self.complete)(self.context, status) }
+    }
+}
+
+/// The async "iterator" we pass to `C#`, for a `Stream` instead of an
+/// `Iterator`.
+///
+/// Instead of blocking until the next item is there, `request_next`
+/// polls the stream once and returns, and the request completes through
+/// its `CSharpCompletion` once the stream has something to say. Whoever
+/// wakes the stream up polls it again right there, so there's no
+/// executor or thread of our own behind it.
+#[repr(C)]
+pub struct CSharpStreamOut<T> {
+    /// The function `C#` calls to ask for the next item, with the slot to
+    /// write it into and the callback to call once it did. Only one
+    /// request can be in flight at a time, another one completes with
+    /// `IterStatus::Error` right away
+    pub request_next: unsafe extern "C" fn(*mut c_void, *mut T, CSharpCompletion),
+    /// A thin pointer to the stream's state that gets leaked
+    pub pointer: *mut c_void,
+    /// The function `C#` calls once it's done with the stream. A request
+    /// still in flight completes with `IterStatus::AlreadyFinished`
+    pub destroy: unsafe extern "C" fn(*mut c_void),
+    /// The function `C#` calls to get the panic message after a request
+    /// completed with `IterStatus::Panicked`
+    pub message: unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> usize,
+    /// The function `C#` calls to hand an item back once it's done with it
+    pub release_item: unsafe extern "C" fn(*mut T),
+}
+
+/// A request `C#` is waiting on
+struct Request<T> {
+    /// Where the item goes, owned by `C#`
+    slot: *mut T,
+    completion: CSharpCompletion,
+}
+
+// The slot is only written once, by whoever completes the request
+unsafe impl<T: Send> Send for Request<T> {}
+
+impl<T> Request<T> {
+    fn complete(self, result: Result<T, IterStatus>) {
+        let status = match result {
+            Ok(x) => {
+                unsafe { std::ptr::write(self.slot, x) };
+                IterStatus::Item
+            }
+            Err(status) => status,
+        };
+        self.completion.complete(status);
+    }
+}
+
+/// The state behind the lock in a `StreamTask`
+struct StreamState<S: Stream> {
+    /// The stream itself, dropped as soon as it finishes or panics
+    stream: Option<Pin<Box<S>>>,
+    /// The request waiting for the stream, if any
+    request: Option<Request<S::Item>>,
+    /// The message of the panic that poisoned this stream, if any
+    panic: Option<String>,
+}
+
+impl<S: Stream> StreamState<S> {
+    /// Polls the stream if there's a request waiting on it, handing the
+    /// request back along with what to complete it with once there's an
+    /// answer
+    #[allow(clippy::type_complexity)]
+    fn poll(&mut self, waker: &Waker) -> Option<(Request<S::Item>, Result<S::Item, IterStatus>)> {
+        // Nobody to hand an item to, so don't pull one out
+        self.request.as_ref()?;
+        let result = match &mut self.stream {
+            Some(stream) => {
+                let mut cx = Context::from_waker(waker);
+                match catch_unwind(AssertUnwindSafe(|| stream.as_mut().poll_next(&mut cx))) {
+                    Ok(Poll::Pending) => return None,
+                    Ok(Poll::Ready(Some(x))) => Ok(x),
+                    Ok(Poll::Ready(None)) => {
+                        self.finish();
+                        match self.panic {
+                            Some(_) => Err(IterStatus::Panicked),
+                            None => Err(IterStatus::Exhausted),
+                        }
+                    }
+                    Err(payload) => {
+                        let message = self.panic.get_or_insert_with(|| panic_message(&*payload));
+                        fail(IterStatus::Panicked, message.as_str());
+                        self.finish();
+                        Err(IterStatus::Panicked)
+                    }
+                }
+            }
+            // A poisoned stream is never touched again
+            None => match &self.panic {
+                Some(message) => Err(fail(IterStatus::Panicked, message.as_str())),
+                None => Err(IterStatus::AlreadyFinished),
+            },
+        };
+        self.request.take().map(|request| (request, result))
+    }
+
+    /// Drops the stream, the same way as `IterState::finish`
+    fn finish(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| drop(stream))) {
+                let message = self.panic.get_or_insert_with(|| panic_message(&*payload));
+                fail(IterStatus::Panicked, message.as_str());
+            }
+        }
+    }
+}
+
+/// What lives behind `CSharpStreamOut::pointer`, and what the stream's
+/// wakers point to
+struct StreamTask<S: Stream> {
+    state: Mutex<StreamState<S>>,
+    /// Set on every wake up, so that a wake up that comes in while
+    /// someone else is polling isn't lost
+    woken: AtomicBool,
+}
+
+impl<S> StreamTask<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    /// Polls the stream until it stops making progress, unless someone
+    /// else already is, in which case they'll poll it again for us
+    fn run(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+        let waker = Waker::from(self.clone());
+        loop {
+            let mut state = match self.state.try_lock() {
+                Ok(state) => state,
+                // Whoever holds the lock checks `woken` after letting go
+                Err(TryLockError::WouldBlock) => return,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            };
+            let mut done = None;
+            while done.is_none() && self.woken.swap(false, Ordering::SeqCst) {
+                done = state.poll(&waker);
+            }
+            drop(state);
+            // Complete outside the lock, since `C#` may well ask for the
+            // next item right from the callback
+            if let Some((request, result)) = done {
+                request.complete(result);
+            }
+            if !self.woken.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+    }
+}
+
+impl<S> Wake for StreamTask<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    fn wake(self: Arc<Self>) {
+        self.run();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.run();
+    }
+}
+
+/// A stock function that asks a stream for its next item.
+///
+/// `data` is treated as uninitialized, the same way as in
+/// `iter_impl_ffi`, and `completion` is always called exactly once, even
+/// when the request couldn't be made.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpStreamOut::form` and not have
+/// been passed to `destroy_stream_impl_ffi` yet, and `data` must be null
+/// or stay valid for writes until `completion` is called.
+pub unsafe extern "C" fn request_next_impl_ffi<S>(p: *mut c_void, data: *mut S::Item, completion: CSharpCompletion)
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    let p = p as *const StreamTask<S>;
+    if p.is_null() {
+        return completion.complete(fail(IterStatus::InvalidHandle, NULL_ITERATOR));
+    }
+    if data.is_null() {
+        return completion.complete(fail(IterStatus::Error, NULL_DATA));
+    }
+    // Borrow the task without taking over the reference `C#` holds
+    let task = ManuallyDrop::new(Arc::from_raw(p));
+    {
+        let mut state = lock(&task.state);
+        if state.request.is_some() {
+            drop(state);
+            return completion.complete(fail(IterStatus::Error, REQUEST_PENDING));
+        }
+        state.request = Some(Request { slot: data, completion });
+    }
+    task.run();
+}
+
+/// A stock function that frees the stream behind a `CSharpStreamOut`,
+/// completing a request still in flight with `IterStatus::AlreadyFinished`.
+/// Wakers the stream handed out stay valid, but do nothing anymore.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpStreamOut::form`, and must not be
+/// used again after this call.
+pub unsafe extern "C" fn destroy_stream_impl_ffi<S>(p: *mut c_void)
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    let p = p as *const StreamTask<S>;
+    if p.is_null() {
+        return;
+    }
+    let task = Arc::from_raw(p);
+    let request = {
+        let mut state = lock(&task.state);
+        state.finish();
+        state.request.take()
+    };
+    if let Some(request) = request {
+        request.complete(Err(IterStatus::AlreadyFinished));
+    }
+}
+
+/// A stock function that copies the panic message of a poisoned stream,
+/// the same way as `message_impl_ffi`.
+///
+/// # Safety
+/// `p` must be null or come from `CSharpStreamOut::form` and not have
+/// been passed to `destroy_stream_impl_ffi` yet, and `buf` must be null
+/// or valid for `len` bytes of writes.
+pub unsafe extern "C" fn message_stream_impl_ffi<S>(p: *mut c_void, buf: *mut u8, len: usize) -> usize
+where
+    S: Stream + Send + 'static,
+    S::Item: Send,
+{
+    let p = p as *const StreamTask<S>;
+    if p.is_null() {
+        return 0;
+    }
+    match &lock(&(*p).state).panic {
+        Some(message) => copy_to_buffer(message, buf, len),
+        None => 0,
+    }
+}
+
+impl<T: Send + 'static> CSharpStreamOut<T> {
+    /// Creates a `CSharpStreamOut<T>` from a stream over `T`. The stream
+    /// is polled on whichever thread asks for an item or wakes it up, so
+    /// it has to be `Send`
+    pub fn form<S: Stream<Item=T> + Send + 'static>(stream: S) -> Self {
+        let task = StreamTask {
+            state: Mutex::new(StreamState {
+                stream: Some(Box::pin(stream)),
+                request: None,
+                panic: None,
+            }),
+            woken: AtomicBool::new(false),
+        };
+        CSharpStreamOut {
+            request_next: request_next_impl_ffi::<S>,
+            pointer: Arc::into_raw(Arc::new(task)) as *mut c_void,
+            destroy: destroy_stream_impl_ffi::<S>,
+            message: message_stream_impl_ffi::<S>,
+            // Uses the stock release function
+            release_item: release_item_impl_ffi,
+        }
+    }
+}
+
+/// A request made from rust, waiting to be completed
+struct Pending<T> {
+    /// Where the item goes. It lives here rather than in the future, so
+    /// that it stays valid even if the future is dropped early
+    slot: UnsafeCell<MaybeUninit<T>>,
+    /// The status once the request completed, and who to wake up then
+    status: Mutex<(Option<IterStatus>, Option<Waker>)>,
+}
+
+impl<T> Drop for Pending<T> {
+    fn drop(&mut self) {
+        // An item nobody took out yet
+        let status = self.status.get_mut().unwrap_or_else(|e| e.into_inner());
+        if status.0 == Some(IterStatus::Item) {
+            unsafe { std::ptr::drop_in_place(self.slot.get_mut().as_mut_ptr()) }
+        }
+    }
+}
+
+/// The completion rust passes when it makes the request itself
+unsafe extern "C" fn complete_impl_ffi<T>(context: *mut c_void, status: IterStatus) {
+    let pending = Arc::from_raw(context as *const Pending<T>);
+    let waker = {
+        let mut state = lock(&pending.status);
+        state.0 = Some(status);
+        state.1.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// The future `CSharpStreamOut::next_item` returns
+struct NextItem<T> {
+    pending: Arc<Pending<T>>,
+}
+
+impl<T> Future for NextItem<T> {
+    type Output = Result<T, IterStatus>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut state = lock(&self.pending.status);
+        match state.0.take() {
+            Some(IterStatus::Item) => Poll::Ready(Ok(unsafe { (*self.pending.slot.get()).as_ptr().read() })),
+            Some(status) => Poll::Ready(Err(status)),
+            None => {
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> CSharpStreamOut<T> {
+    /// Asks for the next item through `request_next`, the same way `C#`
+    /// would, resolving to the item or whatever else the request
+    /// completed with. The request is made right away, not when the
+    /// future is first polled.
+    ///
+    /// # Safety
+    /// `self` must be a live stream from `form`, and `destroy` must not be
+    /// called on it before the future resolves.
+    pub unsafe fn next_item(&mut self) -> impl Future<Output=Result<T, IterStatus>> {
+        let pending = Arc::new(Pending {
+            slot: UnsafeCell::new(MaybeUninit::uninit()),
+            status: Mutex::new((None, None)),
+        });
+        let context = Arc::into_raw(pending.clone()) as *mut c_void;
+        let slot = (*pending.slot.get()).as_mut_ptr();
+        (self.request_next)(self.pointer, slot, CSharpCompletion::new(context, complete_impl_ffi::<T>));
+        NextItem { pending }
+    }
+}
+
+/// Wakes up the thread `block_on` is parked on
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread, parking it while
+/// the future waits. This is all the executor the tests need, since
+/// streams get polled by whoever wakes them up anyway.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+            return x;
+        }
+        thread::park();
+    }
+}
@@ -0,0 +1,119 @@
+use std::ops::Deref;
+
+use crate::CSharpIteratorOut;
+
+/// A string handed to `C#` as a pointer and a length in code units,
+/// either UTF-8 bytes (`FfiUtf8`) or UTF-16 chars (`FfiUtf16`, which is
+/// what `C#` strings are made of). There's no nul terminator.
+///
+/// Each one is its own allocation, owned by whoever holds it: it stays
+/// valid until it's passed to `release_item` or to `ffi_utf8_free` /
+/// `ffi_utf16_free`, even after the iterator it came from is destroyed.
+#[repr(C)]
+pub struct FfiString<C> {
+    /// The pointer to the first code unit, dangling when `len` is 0
+    pub(crate) ptr: *mut C,
+    /// How many code units there are
+    pub(crate) len: usize,
+}
+
+/// A UTF-8 string handed to `C#`
+pub type FfiUtf8 = FfiString<u8>;
+/// A UTF-16 string handed to `C#`, ready to become a `string`
+pub type FfiUtf16 = FfiString<u16>;
+
+// The same as `Box<[C]>`, since that's all this is
+unsafe impl<C: Send> Send for FfiString<C> {}
+unsafe impl<C: Sync> Sync for FfiString<C> {}
+
+impl<C> FfiString<C> {
+    /// Takes over the code units as they are. Crate-private, since an
+    /// `FfiUtf8` promises its bytes are valid UTF-8
+    pub(crate) fn from_units(units: Box<[C]>) -> Self {
+        let len = units.len();
+        FfiString {
+            ptr: Box::into_raw(units) as *mut C,
+            len,
+        }
+    }
+}
+
+impl From<String> for FfiUtf8 {
+    fn from(s: String) -> Self {
+        FfiString::from_units(s.into_bytes().into_boxed_slice())
+    }
+}
+
+impl From<&str> for FfiUtf8 {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+impl From<&str> for FfiUtf16 {
+    fn from(s: &str) -> Self {
+        FfiString::from_units(s.encode_utf16().collect())
+    }
+}
+
+impl FfiUtf8 {
+    /// The string itself, which is always valid UTF-8
+    pub fn as_str(&self) -> &str {
+        unsafe { std::str::from_utf8_unchecked(self) }
+    }
+}
+
+impl<C> Deref for FfiString<C> {
+    type Target = [C];
+
+    fn deref(&self) -> &[C] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<C> Drop for FfiString<C> {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.ptr, self.len))) }
+    }
+}
+
+impl std::fmt::Debug for FfiUtf8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl std::fmt::Debug for FfiUtf16 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        String::from_utf16_lossy(self).fmt(f)
+    }
+}
+
+/// Frees an `FfiUtf8` that was handed to `C#`.
+#[no_mangle]
+pub extern "C" fn ffi_utf8_free(s: FfiUtf8) {
+    drop(s);
+}
+
+/// Frees an `FfiUtf16` that was handed to `C#`.
+#[no_mangle]
+pub extern "C" fn ffi_utf16_free(s: FfiUtf16) {
+    drop(s);
+}
+
+impl CSharpIteratorOut<FfiUtf8> {
+    /// Creates a `CSharpIteratorOut<FfiUtf8>` from an iterator over
+    /// strings, handing each one to `C#` as UTF-8
+    pub fn form_utf8<S: Into<String>, D: Iterator<Item=S> + 'static>(iter: D) -> Self {
+        Self::form(iter.map(|s| FfiUtf8::from(s.into())))
+    }
+}
+
+impl CSharpIteratorOut<FfiUtf16> {
+    /// Creates a `CSharpIteratorOut<FfiUtf16>` from an iterator over
+    /// strings, transcoding each one to UTF-16 so `C#` can turn it into
+    /// a `string` without decoding it
+    pub fn form_utf16<S: AsRef<str>, D: Iterator<Item=S> + 'static>(iter: D) -> Self {
+        Self::form(iter.map(|s| FfiUtf16::from(s.as_ref())))
+    }
+}
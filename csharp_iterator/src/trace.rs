@@ -0,0 +1,54 @@
+use std::fmt::Debug;
+
+use crate::CSharpIteratorOut;
+
+/// An iterator that prints everything it hands out to stderr, along with
+/// when it runs out. This is the only place item types need to be `Debug`
+pub struct Traced<I> {
+    /// The iterator being traced
+    iter: I,
+    /// What to call the iterator in the output
+    name: &'static str,
+    /// How many items were handed out so far
+    count: usize,
+}
+
+impl<I: Iterator> Iterator for Traced<I>
+where
+    I::Item: Debug,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        match self.iter.next() {
+            Some(x) => {
+                eprintln!("[cs_iter] {} #{}: {:?}", self.name, self.count, x);
+                self.count += 1;
+                Some(x)
+            }
+            None => {
+                eprintln!("[cs_iter] {} exhausted after {} items", self.name, self.count);
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I> Drop for Traced<I> {
+    fn drop(&mut self) {
+        eprintln!("[cs_iter] {} dropped", self.name);
+    }
+}
+
+impl<T: Debug> CSharpIteratorOut<T> {
+    /// Creates a `CSharpIteratorOut<T>` like `form`, but prints every item
+    /// `C#` gets, when the iterator runs out, and when it's dropped, to
+    /// stderr, under `name`
+    pub fn form_traced<D: Iterator<Item=T> + 'static>(iter: D, name: &'static str) -> Self {
+        Self::form(Traced { iter, name, count: 0 })
+    }
+}
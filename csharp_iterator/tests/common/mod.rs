@@ -0,0 +1,61 @@
+//! Calls into rust the way `C#` would. Shared by the integration tests
+//! here and the unit tests in `src`, which include this file as a module.
+
+#![allow(dead_code)]
+
+use std::mem::MaybeUninit;
+
+use cs_iter::{last_error_code, last_error_message, CSharpIteratorOut, IterStatus};
+
+/// Reads a message the way `C#` does: once for the length, then again
+/// into a buffer of that length
+pub fn read_message(copy: impl Fn(*mut u8, usize) -> usize) -> String {
+    let len = copy(std::ptr::null_mut(), 0);
+    let mut buf = vec![0; len];
+    copy(buf.as_mut_ptr(), len);
+    String::from_utf8(buf).unwrap()
+}
+
+/// The last error on this thread
+pub fn last_error() -> (IterStatus, String) {
+    (last_error_code(), read_message(|buf, len| unsafe { last_error_message(buf, len) }))
+}
+
+/// The panic or error message of `cs`
+pub fn message<T>(cs: &CSharpIteratorOut<T>) -> String {
+    read_message(|buf, len| unsafe { (cs.message)(cs.pointer, buf, len) })
+}
+
+/// Calls `internal_iter`
+pub fn next<T>(cs: &CSharpIteratorOut<T>) -> (Option<T>, IterStatus) {
+    let mut item = MaybeUninit::uninit();
+    match unsafe { (cs.internal_iter)(cs.pointer, item.as_mut_ptr()) } {
+        IterStatus::Item => (Some(unsafe { item.assume_init() }), IterStatus::Item),
+        status => (None, status),
+    }
+}
+
+/// Calls `next_chunk`, returning the items it wrote
+pub fn next_chunk<T>(cs: &CSharpIteratorOut<T>, len: usize) -> (Vec<T>, IterStatus) {
+    let mut buf: Vec<MaybeUninit<T>> = (0..len).map(|_| MaybeUninit::uninit()).collect();
+    let mut status = IterStatus::Item;
+    let written = unsafe { (cs.next_chunk)(cs.pointer, buf.as_mut_ptr().cast(), len, &mut status) };
+    let items = buf.into_iter().take(written).map(|x| unsafe { x.assume_init() }).collect();
+    (items, status)
+}
+
+/// Calls `internal_iter` until it says something other than `Item`
+pub fn collect<T>(cs: &CSharpIteratorOut<T>) -> (Vec<T>, IterStatus) {
+    let mut items = Vec::new();
+    loop {
+        match next(cs) {
+            (Some(x), _) => items.push(x),
+            (None, status) => return (items, status),
+        }
+    }
+}
+
+/// Calls `destroy`, after which `cs` mustn't be used anymore
+pub fn destroy<T>(cs: &CSharpIteratorOut<T>) {
+    unsafe { (cs.destroy)(cs.pointer) }
+}
@@ -0,0 +1,89 @@
+use std::mem::MaybeUninit;
+
+use cs_iter::{export_iterator, last_error_code, CSharpIteratorOut, IterStatus};
+
+use common::{collect, destroy, last_error};
+
+mod common;
+
+#[export_iterator]
+fn words(text: String) -> impl Iterator<Item=u32> {
+    text.split_whitespace().map(|word| word.len() as u32).collect::<Vec<_>>().into_iter()
+}
+
+#[export_iterator]
+fn repeated(items: Vec<u32>, times: usize) -> impl ExactSizeIterator<Item=u32> {
+    items.repeat(times).into_iter()
+}
+
+#[export_iterator]
+fn broken() -> impl Iterator<Item=u32> {
+    panic!("constructor went wrong");
+    #[allow(unreachable_code)]
+    std::iter::empty()
+}
+
+/// Reads every item out of an iterator that was written to `out`, and
+/// destroys it
+fn read_out(out: MaybeUninit<CSharpIteratorOut<u32>>) -> Vec<u32> {
+    let cs = unsafe { out.assume_init() };
+    let (items, status) = collect(&cs);
+    assert_eq!(status, IterStatus::Exhausted);
+    destroy(&cs);
+    items
+}
+
+#[test]
+fn arguments_are_copied() {
+    let text = "three short words";
+    let mut out = MaybeUninit::uninit();
+    assert!(unsafe { words(out.as_mut_ptr(), text.as_ptr(), text.len()) });
+    assert_eq!(last_error_code(), IterStatus::Item);
+    assert_eq!(read_out(out), [5, 5, 5]);
+
+    let items = [1, 2];
+    let mut out = MaybeUninit::uninit();
+    assert!(unsafe { repeated(out.as_mut_ptr(), items.as_ptr(), items.len(), 2) });
+    assert_eq!(read_out(out), [1, 2, 1, 2]);
+}
+
+/// An empty argument can be null
+#[test]
+fn null_and_empty() {
+    let mut out = MaybeUninit::uninit();
+    assert!(unsafe { words(out.as_mut_ptr(), std::ptr::null(), 0) });
+    assert_eq!(read_out(out), []);
+}
+
+#[test]
+fn invalid_utf8() {
+    let text = [b'a', 0xFF, b'b'];
+    let mut out = MaybeUninit::uninit();
+    assert!(!unsafe { words(out.as_mut_ptr(), text.as_ptr(), text.len()) });
+    let (code, message) = last_error();
+    assert_eq!(code, IterStatus::Error);
+    assert!(message.starts_with("argument `text` isn't valid UTF-8"), "{}", message);
+}
+
+#[test]
+fn null_with_length() {
+    let mut out = MaybeUninit::uninit();
+    assert!(!unsafe { repeated(out.as_mut_ptr(), std::ptr::null(), 3, 1) });
+    assert_eq!(last_error(), (IterStatus::Error, "argument `items` is null".to_string()));
+
+    assert!(!unsafe { words(out.as_mut_ptr(), std::ptr::null(), 3) });
+    assert_eq!(last_error(), (IterStatus::Error, "argument `text` is null".to_string()));
+}
+
+#[test]
+fn null_out() {
+    assert!(!unsafe { repeated(std::ptr::null_mut(), std::ptr::null(), 0, 1) });
+    assert_eq!(last_error(), (IterStatus::Error, "the out pointer is null".to_string()));
+}
+
+#[test]
+fn panicking_body() {
+    let mut out = MaybeUninit::uninit();
+    assert!(!unsafe { broken(out.as_mut_ptr()) });
+    assert_eq!(last_error(), (IterStatus::Panicked, "constructor went wrong".to_string()));
+}
@@ -0,0 +1,77 @@
+use std::ffi::c_void;
+
+use cs_iter::ForeignIterator;
+
+/// What `C#` would keep behind the context pointer
+#[derive(Default)]
+struct Source {
+    /// The items left to hand out
+    items: Vec<u32>,
+    /// How often `next` was called after it returned false
+    calls_after_end: usize,
+    ended: bool,
+    releases: usize,
+}
+
+unsafe extern "C" fn next(context: *mut c_void, data: *mut u32) -> bool {
+    let source = &mut *(context as *mut Source);
+    if source.ended {
+        source.calls_after_end += 1;
+    }
+    match source.items.pop() {
+        Some(x) => {
+            data.write(x);
+            true
+        }
+        None => {
+            source.ended = true;
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn release(context: *mut c_void) {
+    (*(context as *mut Source)).releases += 1;
+}
+
+fn foreign(source: &mut Source, release: Option<unsafe extern "C" fn(*mut c_void)>) -> ForeignIterator<u32> {
+    unsafe { ForeignIterator::new(source as *mut Source as *mut c_void, next, release) }
+}
+
+#[test]
+fn read_to_the_end() {
+    let mut source = Source { items: vec![3, 2, 1], ..Source::default() };
+    let mut iter = foreign(&mut source, Some(release));
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [1, 2, 3]);
+    // Asking again doesn't go back to `C#`
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next(), None);
+    drop(iter);
+    assert!(source.ended);
+    assert_eq!(source.calls_after_end, 0);
+    assert_eq!(source.releases, 1);
+}
+
+#[test]
+fn released_on_early_drop() {
+    let mut source = Source { items: vec![3, 2, 1], ..Source::default() };
+    let mut iter = foreign(&mut source, Some(release));
+    assert_eq!(iter.next(), Some(1));
+    drop(iter);
+    assert_eq!(source.releases, 1);
+    assert_eq!(source.items, [3, 2]);
+
+    // Or before it's read at all
+    drop(foreign(&mut source, Some(release)));
+    assert_eq!(source.releases, 2);
+    assert_eq!(source.items, [3, 2]);
+}
+
+#[test]
+fn release_can_be_null() {
+    let mut source = Source { items: vec![2, 1], ..Source::default() };
+    let sum: u32 = foreign(&mut source, None).sum();
+    assert_eq!(sum, 3);
+    assert_eq!(source.releases, 0);
+    assert_eq!(source.calls_after_end, 0);
+}
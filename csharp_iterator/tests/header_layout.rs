@@ -0,0 +1,77 @@
+use std::fmt::Write;
+use std::fs;
+use std::io::ErrorKind;
+use std::mem::{align_of, offset_of, size_of};
+use std::path::Path;
+use std::process::Command;
+
+use cs_iter::{CSharpIteratorOut, CSharpSink, FfiLayout, FfiSizeHint, FfiUtf16, FfiVec, ForeignIterator, IterStatus};
+use cs_iter_bindgen::{crate_sources, generate_header};
+
+/// Asserts in C that `$c` has the size and alignment of `$rust`, and
+/// that each of the fields is where rust put it
+macro_rules! layout {
+    ($out:expr, $rust:ty => $c:literal $(, $field:ident)*) => {{
+        writeln!($out, "_Static_assert(sizeof({}) == {}, \"size of {}\");", $c, size_of::<$rust>(), $c).unwrap();
+        writeln!($out, "_Static_assert(_Alignof({}) == {}, \"alignment of {}\");", $c, align_of::<$rust>(), $c).unwrap();
+        $(
+            writeln!(
+                $out,
+                "_Static_assert(offsetof({}, {}) == {}, \"offset of {}.{}\");",
+                $c,
+                stringify!($field),
+                offset_of!($rust, $field),
+                $c,
+                stringify!($field)
+            )
+            .unwrap();
+        )*
+    }};
+}
+
+/// The header describes the same layout rust uses, checked by having a C
+/// compiler agree with `size_of`, `align_of` and `offset_of`. Skipped
+/// when there's no `cc` to compile with.
+#[test]
+fn header_matches_rust() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let sources = crate_sources(&manifest_dir.join("src/lib.rs")).unwrap();
+    let header = generate_header(&sources).unwrap();
+
+    let mut check = String::from("#include <stddef.h>\n#include \"cs_iter.h\"\n\n");
+    layout!(check, IterStatus => "IterStatus");
+    layout!(check, FfiSizeHint => "FfiSizeHint", lower, upper, has_upper, exact);
+    layout!(check, FfiVec<usize> => "FfiVec_usize");
+    layout!(check, FfiVec<u8> => "FfiVec_u8");
+    layout!(check, FfiUtf16 => "FfiUtf16");
+    layout!(check, ForeignIterator<u64> => "ForeignIterator_u64");
+    layout!(check, CSharpSink<u64> => "CSharpSink_u64");
+    layout!(check, FfiLayout => "FfiLayout");
+    layout!(check, CSharpIteratorOut<FfiUtf16> => "CSharpIteratorOut_FfiUtf16");
+    layout!(check, CSharpIteratorOut<FfiVec<usize>> => "CSharpIteratorOut_FfiVec_usize");
+    layout!(
+        check,
+        CSharpIteratorOut<u64> => "CSharpIteratorOut_u64",
+        internal_iter,
+        pointer,
+        destroy,
+        message,
+        release_item,
+        size_hint,
+        next_chunk
+    );
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("header_layout");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("cs_iter.h"), header).unwrap();
+    fs::write(dir.join("check.c"), check).unwrap();
+    let output = match Command::new("cc").args(["-std=c11", "-Wall", "-Werror", "-c", "check.c", "-o", "check.o"]).current_dir(&dir).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("no cc to compile the header with, skipping");
+            return;
+        }
+        Err(e) => panic!("couldn't run cc: {}", e),
+    };
+    assert!(output.status.success(), "the header doesn't match rust's layout:\n{}", String::from_utf8_lossy(&output.stderr));
+}
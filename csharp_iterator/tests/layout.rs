@@ -0,0 +1,141 @@
+use std::mem::{size_of, MaybeUninit};
+
+use cs_iter::{abi_layout, abi_layout_info, FfiLayout};
+
+/// The size of a pointer, which is what almost every field is
+const P: usize = size_of::<usize>();
+
+/// The entry for `ty` itself, followed by the ones for its fields
+fn layout_of(ty: &str) -> (FfiLayout, Vec<FfiLayout>) {
+    let layouts: Vec<_> = abi_layout().into_iter().filter(|layout| layout.ty() == ty).collect();
+    let (whole, fields) = layouts.split_first().unwrap_or_else(|| panic!("`{}` isn't in the table", ty));
+    assert_eq!(whole.field(), None, "`{}` doesn't start with the type itself", ty);
+    (*whole, fields.to_vec())
+}
+
+/// Checks that `ty` is exactly `fields`, each one pointer-sized, one
+/// after the other, which is what `C#` declares them as
+fn assert_pointers(ty: &str, fields: &[&str]) {
+    let (whole, actual) = layout_of(ty);
+    assert_eq!((whole.size(), whole.align()), (fields.len() * P, P), "size and alignment of `{}`", ty);
+    let names: Vec<_> = actual.iter().map(|layout| layout.field().unwrap()).collect();
+    assert_eq!(names, fields, "fields of `{}`", ty);
+    for (i, layout) in actual.iter().enumerate() {
+        assert_eq!((layout.offset(), layout.size(), layout.align()), (i * P, P, P), "`{}.{}`", ty, fields[i]);
+    }
+}
+
+#[test]
+fn iterators_are_pointers() {
+    assert_pointers(
+        "CSharpIteratorOut<T>",
+        &["internal_iter", "pointer", "destroy", "message", "release_item", "size_hint", "next_chunk"],
+    );
+    assert_pointers(
+        "CSharpDoubleEndedIteratorOut<T>",
+        &["internal_iter", "pointer", "destroy", "message", "release_item", "size_hint", "next_chunk", "next_back"],
+    );
+    assert_pointers("CSharpBorrowedIteratorOut<T>", &["internal_iter", "pointer", "destroy", "message", "size_hint"]);
+    assert_pointers("CSharpStreamOut<T>", &["request_next", "pointer", "destroy", "message", "release_item"]);
+}
+
+/// The handle is a `u64`, which only lines up with the pointers around
+/// it on 64 bit targets
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn handles_are_pointers() {
+    assert_pointers(
+        "CSharpIteratorHandle<T>",
+        &["internal_iter", "handle", "destroy", "message", "release_item", "size_hint", "next_chunk"],
+    );
+}
+
+#[test]
+fn callbacks_are_pointers() {
+    assert_pointers("ForeignIterator<T>", &["context", "next", "release"]);
+    assert_pointers("CSharpSink<T>", &["context", "push"]);
+    assert_pointers("CSharpCompletion", &["context", "complete"]);
+}
+
+/// Unlike `Vec<T>`, whose field order is up to the compiler
+#[test]
+fn collections_are_pointer_and_lengths() {
+    assert_pointers("FfiVec<T>", &["ptr", "len", "capacity"]);
+    assert_pointers("FfiString<C>", &["ptr", "len"]);
+    assert_pointers("BorrowedSlice<E>", &["ptr", "len"]);
+    assert_pointers("FfiLayout", &["ty", "field", "size", "align", "offset"]);
+}
+
+#[test]
+fn size_hint() {
+    let (whole, fields) = layout_of("FfiSizeHint");
+    // The two bools are padded out to a whole pointer
+    assert_eq!((whole.size(), whole.align()), (3 * P, P));
+    let fields: Vec<_> = fields.iter().map(|layout| (layout.field().unwrap(), layout.offset(), layout.size())).collect();
+    assert_eq!(fields, [("lower", 0, P), ("upper", P, P), ("has_upper", 2 * P, 1), ("exact", 2 * P + 1, 1)]);
+}
+
+/// `C#` declares it as an `int` enum
+#[test]
+fn iter_status_is_an_int() {
+    let (whole, fields) = layout_of("IterStatus");
+    assert_eq!((whole.size(), whole.align()), (4, 4));
+    assert!(fields.is_empty());
+}
+
+/// Every field lies inside its type, after the one before it
+#[test]
+fn fields_are_in_order() {
+    let layouts = abi_layout();
+    let mut whole = layouts[0];
+    let mut end = 0;
+    for layout in layouts {
+        if layout.field().is_none() {
+            assert!(layout.size() % layout.align() == 0 && layout.align().is_power_of_two(), "`{}`", layout.ty());
+            whole = layout;
+            end = 0;
+            continue;
+        }
+        assert_eq!(layout.ty(), whole.ty());
+        assert!(layout.offset() >= end, "`{}.{}` overlaps the field before it", layout.ty(), layout.field().unwrap());
+        assert_eq!(layout.offset() % layout.align(), 0, "`{}.{}` is misaligned", layout.ty(), layout.field().unwrap());
+        end = layout.offset() + layout.size();
+        assert!(end <= whole.size(), "`{}.{}` sticks out of its type", layout.ty(), layout.field().unwrap());
+    }
+}
+
+#[test]
+fn types_are_listed_once() {
+    let mut types: Vec<_> = abi_layout().iter().filter(|layout| layout.field().is_none()).map(FfiLayout::ty).collect();
+    let count = types.len();
+    types.sort_unstable();
+    types.dedup();
+    assert_eq!(types.len(), count);
+}
+
+#[test]
+fn info_matches_layout() {
+    let expected = abi_layout();
+    let count = unsafe { abi_layout_info(std::ptr::null_mut(), 0) };
+    assert_eq!(count, expected.len());
+
+    let mut buf = vec![MaybeUninit::<FfiLayout>::uninit(); count];
+    assert_eq!(unsafe { abi_layout_info(buf.as_mut_ptr().cast(), count) }, count);
+    for (actual, expected) in buf.iter().zip(&expected) {
+        let actual = unsafe { actual.assume_init() };
+        assert_eq!((actual.ty(), actual.field()), (expected.ty(), expected.field()));
+        assert_eq!((actual.size(), actual.align(), actual.offset()), (expected.size(), expected.align(), expected.offset()));
+    }
+}
+
+/// A buffer that's too short gets as many entries as fit, and nothing past them
+#[test]
+fn info_stops_at_len() {
+    let mut buf = [MaybeUninit::<FfiLayout>::zeroed(); 3];
+    let count = unsafe { abi_layout_info(buf.as_mut_ptr().cast(), 2) };
+    assert!(count > 2);
+    assert_eq!(unsafe { buf[0].assume_init() }.ty(), "IterStatus");
+    assert_eq!(unsafe { buf[1].assume_init() }.ty(), "FfiSizeHint");
+    let untouched = unsafe { std::slice::from_raw_parts(buf[2].as_ptr().cast::<u8>(), size_of::<FfiLayout>()) };
+    assert!(untouched.iter().all(|b| *b == 0));
+}
@@ -0,0 +1,81 @@
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use cs_iter::{CSharpIteratorOut, IterStatus};
+
+use common::{collect, destroy, message, next};
+
+mod common;
+
+/// Sends the name of the thread it's dropped on
+struct DropGuard(Sender<Option<String>>);
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        let _ = self.0.send(thread::current().name().map(str::to_string));
+    }
+}
+
+/// `0..count`, along with a receiver that hears from the producer once
+/// it drops the iterator
+fn guarded(count: u64) -> (impl Iterator<Item=u64> + Send, std::sync::mpsc::Receiver<Option<String>>) {
+    let (tx, rx) = channel();
+    let guard = DropGuard(tx);
+    let iter = (0..count).inspect(move |_| {
+        let _ = &guard;
+    });
+    (iter, rx)
+}
+
+#[test]
+fn items_in_order() {
+    let cs = CSharpIteratorOut::form_prefetching(0..100u32, 4);
+    assert_eq!(collect(&cs), ((0..100).collect(), IterStatus::Exhausted));
+    assert_eq!(next(&cs), (None, IterStatus::AlreadyFinished));
+    destroy(&cs);
+}
+
+/// The panic is raised again when `C#` gets to it, after the items
+/// produced before it
+#[test]
+fn panic_is_passed_on() {
+    let cs = CSharpIteratorOut::form_prefetching((0..10u32).inspect(|&x| assert!(x < 3, "producer {}", x)), 8);
+    assert_eq!(collect(&cs), (vec![0, 1, 2], IterStatus::Panicked));
+    assert_eq!(message(&cs), "producer 3");
+    assert_eq!(next(&cs), (None, IterStatus::Panicked));
+    destroy(&cs);
+}
+
+/// Running out drops the iterator on the producer thread, never on the
+/// one `C#` calls from
+#[test]
+fn dropped_on_producer() {
+    let (iter, dropped) = guarded(3);
+    let cs = CSharpIteratorOut::form_prefetching(iter, 1);
+    assert_eq!(collect(&cs), (vec![0, 1, 2], IterStatus::Exhausted));
+    assert_eq!(dropped.recv_timeout(Duration::from_secs(5)).unwrap().as_deref(), Some("cs_iter prefetch"));
+    destroy(&cs);
+}
+
+/// Destroying the iterator early gets the producer out of the `send`
+/// it's blocked on, and it drops the iterator on its own thread
+#[test]
+fn destroyed_while_producer_blocked() {
+    let (iter, dropped) = guarded(u64::MAX);
+    let cs = CSharpIteratorOut::form_prefetching(iter, 1);
+    assert_eq!(next(&cs), (Some(0), IterStatus::Item));
+    // Give the producer time to fill the channel and block on the item after
+    thread::sleep(Duration::from_millis(50));
+    assert!(dropped.try_recv().is_err());
+    destroy(&cs);
+    assert_eq!(dropped.recv_timeout(Duration::from_secs(5)).unwrap().as_deref(), Some("cs_iter prefetch"));
+}
+
+/// A rendezvous channel still works, handing over one item at a time
+#[test]
+fn zero_capacity() {
+    let cs = CSharpIteratorOut::form_prefetching(0..5u32, 0);
+    assert_eq!(collect(&cs), (vec![0, 1, 2, 3, 4], IterStatus::Exhausted));
+    destroy(&cs);
+}
@@ -0,0 +1,97 @@
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::rc::Rc;
+
+use cs_iter::{drive_into_callback, CSharpIteratorOut, CSharpSink, IterStatus};
+
+use common::last_error;
+
+mod common;
+
+/// Counts how many times it was dropped
+struct Counted(u32, Rc<Cell<usize>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+/// What `C#` would keep behind the context pointer
+struct Collector {
+    seen: Vec<u32>,
+    /// How many items to take before stopping
+    wanted: usize,
+}
+
+unsafe extern "C" fn push(context: *mut c_void, item: *const Counted) -> bool {
+    let collector = &mut *(context as *mut Collector);
+    collector.seen.push((*item).0);
+    collector.seen.len() < collector.wanted
+}
+
+unsafe extern "C" fn push_u32(context: *mut c_void, item: *const u32) -> bool {
+    (*(context as *mut Vec<u32>)).push(*item);
+    true
+}
+
+fn counted(count: u32, drops: &Rc<Cell<usize>>) -> Vec<Counted> {
+    (0..count).map(|x| Counted(x, drops.clone())).collect()
+}
+
+#[test]
+fn full_run_is_exhausted() {
+    let drops = Rc::new(Cell::new(0));
+    let mut collector = Collector { seen: Vec::new(), wanted: usize::MAX };
+    let sink = unsafe { CSharpSink::new(&mut collector as *mut Collector as *mut c_void, push) };
+    assert_eq!(drive_into_callback(counted(4, &drops), &sink), IterStatus::Exhausted);
+    assert_eq!(collector.seen, [0, 1, 2, 3]);
+    assert_eq!(drops.get(), 4);
+}
+
+#[test]
+fn early_stop_drops_the_rest() {
+    let drops = Rc::new(Cell::new(0));
+    let mut collector = Collector { seen: Vec::new(), wanted: 2 };
+    let sink = unsafe { CSharpSink::new(&mut collector as *mut Collector as *mut c_void, push) };
+    assert_eq!(drive_into_callback(counted(5, &drops), &sink), IterStatus::Item);
+    assert_eq!(collector.seen, [0, 1]);
+    assert_eq!(drops.get(), 5);
+}
+
+#[test]
+fn panic_is_recorded() {
+    let mut seen: Vec<u32> = Vec::new();
+    let sink = unsafe { CSharpSink::new(&mut seen as *mut Vec<u32> as *mut c_void, push_u32) };
+    let iter = (0..4u32).map(|x| if x == 2 { panic!("sink") } else { x });
+    assert_eq!(drive_into_callback(iter, &sink), IterStatus::Panicked);
+    assert_eq!(seen, [0, 1]);
+    assert_eq!(last_error(), (IterStatus::Panicked, "sink".to_string()));
+}
+
+#[test]
+fn iterator_out_releases_and_destroys() {
+    let drops = Rc::new(Cell::new(0));
+    let mut collector = Collector { seen: Vec::new(), wanted: usize::MAX };
+    let sink = unsafe { CSharpSink::new(&mut collector as *mut Collector as *mut c_void, push) };
+    let cs = CSharpIteratorOut::form(counted(3, &drops).into_iter());
+    assert_eq!(unsafe { cs.drive_into_callback(&sink) }, IterStatus::Exhausted);
+    assert_eq!(collector.seen, [0, 1, 2]);
+    assert_eq!(drops.get(), 3);
+
+    // Stopping early releases what was pushed, and `destroy` drops the
+    // items never reached
+    collector = Collector { seen: Vec::new(), wanted: 1 };
+    let sink = unsafe { CSharpSink::new(&mut collector as *mut Collector as *mut c_void, push) };
+    let cs = CSharpIteratorOut::form(counted(3, &drops).into_iter());
+    assert_eq!(unsafe { cs.drive_into_callback(&sink) }, IterStatus::Item);
+    assert_eq!(collector.seen, [0]);
+    assert_eq!(drops.get(), 6);
+
+    let mut seen: Vec<u32> = Vec::new();
+    let sink = unsafe { CSharpSink::new(&mut seen as *mut Vec<u32> as *mut c_void, push_u32) };
+    let cs = CSharpIteratorOut::form((0..4u32).map(|x| if x == 2 { panic!("sink") } else { x }));
+    assert_eq!(unsafe { cs.drive_into_callback(&sink) }, IterStatus::Panicked);
+    assert_eq!(seen, [0, 1]);
+    assert_eq!(last_error(), (IterStatus::Panicked, "sink".to_string()));
+}
@@ -0,0 +1,170 @@
+use std::ffi::c_void;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+use cs_iter::{block_on, export_into, get_ticking_stream, CSharpCompletion, CSharpStreamOut, IterStatus};
+use futures_channel::mpsc;
+use futures_core::Stream;
+
+use common::last_error;
+
+mod common;
+
+/// Yields `0..count`, but returns `Pending` before each item, waking
+/// itself up from inside `poll_next` the way a yield point would
+struct Yielding {
+    next: u32,
+    count: u32,
+    ready: bool,
+}
+
+impl Stream for Yielding {
+    type Item = u32;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u32>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        if self.next == self.count {
+            return Poll::Ready(None);
+        }
+        self.next += 1;
+        Poll::Ready(Some(self.next - 1))
+    }
+}
+
+/// Panics on its first poll
+struct Panicking;
+
+impl Stream for Panicking {
+    type Item = u32;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<u32>> {
+        panic!("stream went wrong")
+    }
+}
+
+/// Collects every item of a stream, along with the status that ended it
+fn collect<T>(out: &mut CSharpStreamOut<T>) -> (Vec<T>, IterStatus) {
+    let mut items = Vec::new();
+    loop {
+        match block_on(unsafe { out.next_item() }) {
+            Ok(x) => items.push(x),
+            Err(status) => return (items, status),
+        }
+    }
+}
+
+/// A completion that counts how often it was called, and with what
+unsafe extern "C" fn record(context: *mut c_void, status: IterStatus) {
+    let calls = &*(context as *const AtomicUsize);
+    calls.fetch_add(1, Ordering::SeqCst);
+    assert_eq!(status, IterStatus::AlreadyFinished);
+}
+
+#[test]
+fn ready_items() {
+    let (tx, rx) = mpsc::unbounded();
+    for x in 0..5 {
+        tx.unbounded_send(x).unwrap();
+    }
+    drop(tx);
+    let mut out = CSharpStreamOut::form(rx);
+    assert_eq!(collect(&mut out), (vec![0, 1, 2, 3, 4], IterStatus::Exhausted));
+    // Exhausted is only reported once
+    assert_eq!(block_on(unsafe { out.next_item() }), Err(IterStatus::AlreadyFinished));
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn self_waking_stream() {
+    let mut out = CSharpStreamOut::form(Yielding { next: 0, count: 4, ready: false });
+    assert_eq!(collect(&mut out), (vec![0, 1, 2, 3], IterStatus::Exhausted));
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn woken_from_another_thread() {
+    let (tx, rx) = mpsc::unbounded();
+    let sender = thread::spawn(move || {
+        for x in 0..20u64 {
+            thread::sleep(Duration::from_millis(1));
+            tx.unbounded_send(format!("item {}", x)).unwrap();
+        }
+    });
+    let mut out = CSharpStreamOut::form(rx);
+    let (items, status) = collect(&mut out);
+    sender.join().unwrap();
+    assert_eq!(status, IterStatus::Exhausted);
+    assert_eq!(items, (0..20).map(|x| format!("item {}", x)).collect::<Vec<_>>());
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn panic_poisons() {
+    let mut out = CSharpStreamOut::form(Panicking);
+    assert_eq!(block_on(unsafe { out.next_item() }), Err(IterStatus::Panicked));
+    // A successful export clears the last error, and every request after
+    // the first one records it again
+    assert!(unsafe { export_into(&mut (), || ()) });
+    assert_eq!(last_error().0, IterStatus::Item);
+    assert_eq!(block_on(unsafe { out.next_item() }), Err(IterStatus::Panicked));
+    assert_eq!(last_error(), (IterStatus::Panicked, "stream went wrong".to_string()));
+    let mut buf = [0u8; 64];
+    let len = unsafe { (out.message)(out.pointer, buf.as_mut_ptr(), buf.len()) };
+    assert_eq!(&buf[..len], b"stream went wrong");
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn one_request_at_a_time() {
+    let (tx, rx) = mpsc::unbounded::<Arc<u32>>();
+    let mut out = CSharpStreamOut::form(rx);
+    let first = unsafe { out.next_item() };
+    assert_eq!(block_on(unsafe { out.next_item() }), Err(IterStatus::Error));
+    tx.unbounded_send(Arc::new(7)).unwrap();
+    assert_eq!(block_on(first).map(|x| *x), Ok(7));
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn destroy_completes_pending_request() {
+    let (_tx, rx) = mpsc::unbounded::<u32>();
+    let out = CSharpStreamOut::form(rx);
+    let calls = AtomicUsize::new(0);
+    let mut slot = 0u32;
+    unsafe {
+        let completion = CSharpCompletion::new(&calls as *const AtomicUsize as *mut c_void, record);
+        (out.request_next)(out.pointer, &mut slot, completion);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        (out.destroy)(out.pointer);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn dropped_future_drops_its_item() {
+    let item = Arc::new(());
+    let (tx, rx) = mpsc::unbounded();
+    tx.unbounded_send(item.clone()).unwrap();
+    let mut out = CSharpStreamOut::form(rx);
+    drop(unsafe { out.next_item() });
+    assert_eq!(Arc::strong_count(&item), 1);
+    unsafe { (out.destroy)(out.pointer) };
+}
+
+#[test]
+fn ticking_stream() {
+    let mut out = std::mem::MaybeUninit::uninit();
+    unsafe { get_ticking_stream(out.as_mut_ptr(), 3) };
+    let mut out = unsafe { out.assume_init() };
+    assert_eq!(collect(&mut out), (vec![0, 1, 2], IterStatus::Exhausted));
+    unsafe { (out.destroy)(out.pointer) };
+}
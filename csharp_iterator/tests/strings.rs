@@ -0,0 +1,57 @@
+use cs_iter::{ffi_utf16_free, ffi_utf8_free, CSharpIteratorOut, FfiUtf16, FfiUtf8, IterStatus};
+
+use common::{collect, destroy};
+
+mod common;
+
+/// Empty, ASCII, non-ASCII, and outside the BMP, which takes a surrogate
+/// pair in UTF-16
+const STRINGS: [&str; 4] = ["", "plain", "grüße, 日本", "🦀 crab"];
+
+#[test]
+fn utf8_round_trip() {
+    let cs = CSharpIteratorOut::form_utf8(STRINGS.iter().copied());
+    let (items, status) = collect(&cs);
+    assert_eq!(status, IterStatus::Exhausted);
+    destroy(&cs);
+    // The strings outlive their iterator
+    let read: Vec<&str> = items.iter().map(FfiUtf8::as_str).collect();
+    assert_eq!(read, STRINGS);
+    for (item, expected) in items.iter().zip(STRINGS) {
+        assert_eq!(&**item, expected.as_bytes());
+        assert_eq!(format!("{:?}", item), format!("{:?}", expected));
+    }
+    let mut items = items.into_iter();
+    let mut first = items.next().unwrap();
+    unsafe { (cs.release_item)(&mut first) };
+    std::mem::forget(first);
+    items.for_each(|s| ffi_utf8_free(s));
+}
+
+#[test]
+fn utf16_round_trip() {
+    let cs = CSharpIteratorOut::form_utf16(STRINGS.iter());
+    let (items, status) = collect(&cs);
+    assert_eq!(status, IterStatus::Exhausted);
+    destroy(&cs);
+    for (item, expected) in items.iter().zip(STRINGS) {
+        assert_eq!(item.len(), expected.encode_utf16().count());
+        assert_eq!(String::from_utf16(item).unwrap(), expected);
+        assert_eq!(format!("{:?}", item), format!("{:?}", expected));
+    }
+    // The crab is a surrogate pair
+    assert_eq!(items[3].len(), "crab".len() + 3);
+    let mut items = items.into_iter();
+    let mut first = items.next().unwrap();
+    unsafe { (cs.release_item)(&mut first) };
+    std::mem::forget(first);
+    items.for_each(|s| ffi_utf16_free(s));
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(FfiUtf8::from(String::from("grüße")).as_str(), "grüße");
+    assert_eq!(FfiUtf8::from("").as_str(), "");
+    assert_eq!(String::from_utf16(&FfiUtf16::from("日本")).unwrap(), "日本");
+    assert!(FfiUtf16::from("").is_empty());
+}